@@ -3,6 +3,8 @@ pub mod traits;
 
 use thiserror::Error;
 
+use crate::types::RatingError;
+
 /// Error wrapper
 #[derive(Error, Debug)]
 pub enum HydrusError {
@@ -14,6 +16,10 @@ pub enum HydrusError {
     IOError(std::io::Error),
     #[error("api or session key needed")]
     KeyNotSupplied,
+    #[error("downloaded file has hash {actual}, expected {expected}")]
+    HashMismatch { expected: String, actual: String },
+    #[error("invalid rating: {0}")]
+    InvalidRating(RatingError),
 }
 
 impl From<serde_json::Error> for HydrusError {
@@ -33,3 +39,9 @@ impl From<reqwest::Error> for HydrusError {
         HydrusError::NetworkError(value)
     }
 }
+
+impl From<RatingError> for HydrusError {
+    fn from(value: RatingError) -> Self {
+        HydrusError::InvalidRating(value)
+    }
+}
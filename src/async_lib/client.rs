@@ -1,12 +1,28 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use async_trait::async_trait;
-use reqwest::{Body, RequestBuilder};
+use futures_util::TryStreamExt;
+use reqwest::{
+    Body, RequestBuilder, StatusCode,
+    header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE},
+};
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio_util::codec::{BytesCodec, FramedRead};
 
 use crate::async_lib::HydrusError;
 use crate::async_lib::traits::*;
+use crate::cache::{TagSearch, TagSearchCache, ThumbnailCache, thumbnail_mime};
+use crate::rules::{DuplicateRules, ResolutionReport, ResolutionRun};
+use crate::types::duplicates::*;
+use crate::types::search::*;
+use crate::types::tags::*;
 use crate::types::*;
 
 type Result<T> = std::result::Result<T, HydrusError>;
@@ -17,6 +33,8 @@ pub struct HydrusClient {
     apikey: Option<String>,
     sessionkey: Option<String>,
     url: String,
+    thumbnail_cache: Option<Arc<ThumbnailCache>>,
+    tag_search_cache: Option<TagSearchCache>,
 }
 
 impl HydrusClient {
@@ -31,6 +49,8 @@ impl HydrusClient {
             apikey: None,
             sessionkey: None,
             url,
+            thumbnail_cache: None,
+            tag_search_cache: None,
         }
     }
     /// set an api key
@@ -41,6 +61,14 @@ impl HydrusClient {
     pub fn set_session_key(&mut self, key: String) {
         self.sessionkey = Some(key.to_owned())
     }
+    /// cache thumbnails requested by hash on disk
+    pub fn set_thumbnail_cache(&mut self, cache: ThumbnailCache) {
+        self.thumbnail_cache = Some(Arc::new(cache))
+    }
+    /// cache tag searches in memory, for autocomplete
+    pub fn set_tag_search_cache(&mut self, cache: TagSearchCache) {
+        self.tag_search_cache = Some(cache)
+    }
 
     fn set_get_request_key(&self, url: &str) -> Result<RequestBuilder> {
         let request = self.client.get(url);
@@ -63,6 +91,42 @@ impl HydrusClient {
             Err(HydrusError::KeyNotSupplied)
         }
     }
+
+    /// request a file, starting at `offset`
+    async fn request_file(
+        &self,
+        file: &SingleFile,
+        download: bool,
+        offset: u64,
+    ) -> Result<FileDownload<ByteStream>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/file");
+
+        let mut request = self
+            .set_get_request_key(&req_url)?
+            .query(&[file.query_param()])
+            .query(&[("download", download)]);
+
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={offset}-"));
+        }
+
+        let response = request.send().await?.error_for_status()?;
+
+        let headers = response.headers();
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let info = DownloadInfo::from_headers(
+            header(CONTENT_TYPE),
+            header(CONTENT_LENGTH),
+            header(CONTENT_RANGE),
+            header(CONTENT_DISPOSITION),
+        );
+
+        Ok(FileDownload {
+            info,
+            body: Box::pin(response.bytes_stream().map_err(std::io::Error::other)),
+        })
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -71,7 +135,17 @@ struct HydrusResponse<T> {
         alias = "service",
         alias = "services",
         alias = "access_key",
-        alias = "session_key"
+        alias = "session_key",
+        alias = "metadata",
+        alias = "locations",
+        alias = "hashes",
+        alias = "tags",
+        alias = "favourite_tags",
+        alias = "notes",
+        alias = "file_relationships",
+        alias = "potential_duplicates_count",
+        alias = "potential_duplicate_pairs",
+        alias = "random_potential_duplicate_hashes"
     )]
     body: T,
 }
@@ -452,4 +526,905 @@ impl ImportingAndEditingUrls for HydrusClient {
 
         Ok(request.send().await?.json::<FilesUrlResponse>().await?)
     }
+
+    async fn add_url(&self, url: &str, options: AddUrlOptions) -> Result<AddUrlResponse> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_urls/add_url");
+
+        let form = AddUrlRequest::new(url, options);
+
+        Ok(self
+            .set_post_request_key(&req_url)?
+            .json(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<AddUrlResponse>()
+            .await?)
+    }
+
+    async fn get_url_info(&self, url: &str) -> Result<UrlInfo> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_urls/get_url_info");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query(&[("url", url)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<UrlInfo>()
+            .await?)
+    }
+
+    async fn associate_url(
+        &self,
+        file: HydrusFile,
+        urls_to_add: Vec<String>,
+        urls_to_delete: Vec<String>,
+        normalise_urls: Option<bool>,
+    ) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_urls/associate_url");
+
+        let form = AssociateUrlRequest {
+            file,
+            urls_to_add,
+            urls_to_delete,
+            normalise_urls,
+        };
+
+        self.set_post_request_key(&req_url)?
+            .json(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SearchingAndFetchingFiles for HydrusClient {
+    async fn search_files(
+        &self,
+        tags: &[SearchTag],
+        domain: Option<FileDomain>,
+        tag_service_key: Option<String>,
+        sort_type: Option<FileSortType>,
+        sort_direction: Option<SortDirection>,
+        returns: SearchReturns,
+    ) -> Result<SearchFilesResponse> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/search_files");
+
+        let mut request = self.set_get_request_key(&req_url)?;
+
+        request = request.query(&[("tags", serde_json::to_string(tags)?)]);
+
+        if let Some(file_domain) = domain {
+            request = request.query(&[file_domain.query_param()]);
+        }
+
+        if let Some(key) = tag_service_key {
+            request = request.query(&[("tag_service_key", key)]);
+        }
+
+        if let Some(sort) = sort_type {
+            request = request.query(&[("file_sort_type", sort as u8)]);
+        }
+
+        if let Some(direction) = sort_direction {
+            request = request.query(&[("file_sort_asc", direction == SortDirection::Ascending)]);
+        }
+
+        let (file_ids, hashes) = match returns {
+            SearchReturns::FileIds => (true, false),
+            SearchReturns::Hashes => (false, true),
+            SearchReturns::Both => (true, true),
+        };
+        request = request.query(&[("return_file_ids", file_ids), ("return_hashes", hashes)]);
+
+        Ok(request
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SearchFilesResponse>()
+            .await?)
+    }
+
+    async fn file_metadata(
+        &self,
+        file: HydrusFile,
+        options: FileMetadataOptions,
+    ) -> Result<Vec<FileMetadata>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/file_metadata");
+
+        let request = self
+            .set_get_request_key(&req_url)?
+            .query(&[file.query_param()])
+            .query(&[
+                ("only_return_identifiers", options.only_return_identifiers),
+                (
+                    "only_return_basic_information",
+                    options.only_return_basic_information,
+                ),
+                ("include_notes", options.include_notes),
+                ("include_blurhash", options.include_blurhash),
+                ("detailed_url_information", options.detailed_url_information),
+                ("include_milliseconds", options.include_milliseconds),
+                ("include_services_object", false),
+            ]);
+
+        Ok(request
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<Vec<FileMetadata>>>()
+            .await?
+            .body)
+    }
+
+    async fn get_file(&self, file: SingleFile, download: bool) -> Result<FileDownload<ByteStream>> {
+        self.request_file(&file, download, 0).await
+    }
+
+    async fn render_file(&self, file: SingleFile, options: RenderOptions) -> Result<RenderedFile> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/render");
+
+        let response = self
+            .set_get_request_key(&req_url)?
+            .query(&[file.query_param()])
+            .query(&options.query_params())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let mime = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+            .or(options.format.map(|format| format.mime().to_string()))
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let bytes = response.bytes().await?.to_vec();
+
+        Ok(RenderedFile { mime, bytes })
+    }
+
+    async fn download_to_path(
+        &self,
+        file: SingleFile,
+        path: PathBuf,
+        progress: Option<&(dyn Fn(DownloadProgress) + Send + Sync)>,
+    ) -> Result<PathBuf> {
+        let is_dir = tokio::fs::metadata(&path)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir());
+        let (path, first) = if is_dir {
+            let download = self.request_file(&file, true, 0).await?;
+            let name = download
+                .info
+                .filename
+                .as_deref()
+                .and_then(|name| Path::new(name).file_name())
+                .ok_or(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    "no file name suggested by the client",
+                ))?;
+            (path.join(name), Some(download))
+        } else {
+            (path, None)
+        };
+        let part = part_path(&path)?;
+        if tokio::fs::try_exists(&path).await? {
+            return Err(already_exists(&path).into());
+        }
+
+        let resume_from = tokio::fs::metadata(&part)
+            .await
+            .map_or(0, |metadata| metadata.len());
+        let download = match first {
+            Some(download) if resume_from == 0 => Some(download),
+            _ => match self.request_file(&file, true, resume_from).await {
+                Ok(download) => Some(download),
+                Err(HydrusError::NetworkError(error))
+                    if resume_from > 0
+                        && error.status() == Some(StatusCode::RANGE_NOT_SATISFIABLE) =>
+                {
+                    match file {
+                        // the part file may hold the whole file, which the hash check tells
+                        SingleFile::Hash(_) => None,
+                        // without a hash the part file cannot be checked, so start over
+                        SingleFile::FileId(_) => Some(self.request_file(&file, true, 0).await?),
+                    }
+                }
+                Err(error) => return Err(error),
+            },
+        };
+
+        // the client may resume from an earlier position than asked for, or send the whole file
+        let start = download
+            .as_ref()
+            .map_or(resume_from, |download| download.info.offset);
+        if start > resume_from {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "the client resumed past the end of the partial file",
+            )
+            .into());
+        }
+
+        let mut output = tokio::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&part)
+            .await?;
+        output.set_len(start).await?;
+        let mut hasher = Sha256::new();
+        let mut existing = (&mut output).take(start);
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = existing.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+
+        if let Some(mut download) = download {
+            let mut done = start;
+            while let Some(chunk) = download.body.try_next().await? {
+                output.write_all(&chunk).await?;
+                hasher.update(&chunk);
+                done += chunk.len() as u64;
+                if let Some(progress) = progress {
+                    progress(DownloadProgress {
+                        done,
+                        total: download.info.size,
+                    });
+                }
+            }
+        }
+        output.flush().await?;
+        drop(output);
+
+        if let SingleFile::Hash(expected) = &file {
+            let actual = format!("{:x}", hasher.finalize());
+            if !actual.eq_ignore_ascii_case(expected) {
+                // a broken part file would otherwise be resumed again
+                tokio::fs::remove_file(&part).await?;
+                return Err(HydrusError::HashMismatch {
+                    expected: expected.to_owned(),
+                    actual,
+                });
+            }
+        }
+
+        if tokio::fs::try_exists(&path).await? {
+            return Err(already_exists(&path).into());
+        }
+        tokio::fs::rename(&part, &path).await?;
+        Ok(path)
+    }
+
+    async fn get_thumbnail(&self, file: SingleFile, blurhash_fallback: bool) -> Result<Thumbnail> {
+        // the cache works on the file system, so it is kept off the runtime's threads
+        let cache_key = match (&file, &self.thumbnail_cache) {
+            (SingleFile::Hash(hash), Some(cache)) => {
+                let (cache, key) = (cache.clone(), hash.to_owned());
+                let cached = tokio::task::spawn_blocking(move || cache.get(&key))
+                    .await
+                    .ok()
+                    .flatten();
+                if let Some(bytes) = cached {
+                    return Ok(Thumbnail::Image {
+                        mime: thumbnail_mime(&bytes),
+                        bytes,
+                    });
+                }
+                Some(hash.to_owned())
+            }
+            _ => None,
+        };
+
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/thumbnail");
+
+        let response = self
+            .set_get_request_key(&req_url)?
+            .query(&[file.query_param()])
+            .send()
+            .await?;
+
+        let response = match response.error_for_status() {
+            Ok(response) => response,
+            Err(error) if blurhash_fallback => {
+                let options = FileMetadataOptions {
+                    include_blurhash: true,
+                    ..Default::default()
+                };
+                return match self
+                    .file_metadata(file.into(), options)
+                    .await?
+                    .into_iter()
+                    .next()
+                    .and_then(|metadata| metadata.blurhash)
+                {
+                    Some(blurhash) => Ok(Thumbnail::Blurhash(blurhash)),
+                    None => Err(error.into()),
+                };
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let mime = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        let bytes = response.bytes().await?.to_vec();
+
+        if let (Some(hash), Some(cache)) = (cache_key, &self.thumbnail_cache) {
+            let (cache, cached) = (cache.clone(), bytes.clone());
+            // a failing cache should not fail the request
+            let _ = tokio::task::spawn_blocking(move || cache.insert(&hash, &cached)).await;
+        }
+
+        Ok(Thumbnail::Image { mime, bytes })
+    }
+
+    async fn file_path(&self, file: SingleFile) -> Result<LocalFilePath> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/file_path");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query(&[file.query_param()])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<LocalFilePath>()
+            .await?)
+    }
+
+    async fn thumbnail_path(&self, file: SingleFile) -> Result<LocalThumbnailPath> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/thumbnail_path");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query(&[file.query_param()])
+            .query(&[("include_thumbnail_filetype", true)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<LocalThumbnailPath>()
+            .await?)
+    }
+
+    async fn local_file_storage_locations(&self) -> Result<Vec<StorageLocation>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/local_file_storage_locations");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<Vec<StorageLocation>>>()
+            .await?
+            .body)
+    }
+
+    async fn file_hashes(
+        &self,
+        hashes: &[String],
+        source: HashType,
+        desired: HashType,
+    ) -> Result<HashMap<String, String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/file_hashes");
+
+        let mut converted = HashMap::new();
+        for batch in hashes.chunks(FILE_HASHES_BATCH_SIZE) {
+            converted.extend(
+                self.set_get_request_key(&req_url)?
+                    .query(&[
+                        ("hashes", serde_json::to_string(batch)?),
+                        ("source_hash_type", source.as_str().to_string()),
+                        ("desired_hash_type", desired.as_str().to_string()),
+                    ])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<HydrusResponse<HashMap<String, String>>>()
+                    .await?
+                    .body,
+            );
+        }
+        Ok(converted)
+    }
+}
+
+#[async_trait]
+impl EditingFileTags for HydrusClient {
+    async fn clean_tags(&self, tags: &[String]) -> Result<Vec<String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/clean_tags");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query(&[("tags", serde_json::to_string(tags)?)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<Vec<String>>>()
+            .await?
+            .body)
+    }
+
+    async fn search_tags(
+        &self,
+        search: &str,
+        tag_service_key: Option<String>,
+        domain: Option<FileDomain>,
+        display_type: TagDisplayType,
+    ) -> Result<Vec<TagCount>> {
+        let key = TagSearch {
+            search: search.to_string(),
+            tag_service_key,
+            domain: domain.map(|domain| domain.query_param()),
+            display_type,
+        };
+        if let Some(tags) = self
+            .tag_search_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&key))
+        {
+            return Ok(tags);
+        }
+
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/search_tags");
+
+        let mut request = self.set_get_request_key(&req_url)?.query(&[
+            ("search", search),
+            ("tag_display_type", display_type.as_str()),
+        ]);
+        if let Some(tag_service_key) = &key.tag_service_key {
+            request = request.query(&[("tag_service_key", tag_service_key)]);
+        }
+        if let Some(domain) = &key.domain {
+            request = request.query(&[domain]);
+        }
+
+        let tags = request
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<Vec<TagCount>>>()
+            .await?
+            .body;
+        if let Some(cache) = &self.tag_search_cache {
+            cache.insert(key, tags.clone());
+        }
+        Ok(tags)
+    }
+
+    async fn get_siblings_and_parents(
+        &self,
+        tags: &[String],
+    ) -> Result<HashMap<String, HashMap<String, TagRelations>>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/get_siblings_and_parents");
+
+        let mut relations = HashMap::new();
+        for batch in tags.chunks(SIBLINGS_AND_PARENTS_BATCH_SIZE) {
+            relations.extend(
+                self.set_get_request_key(&req_url)?
+                    .query(&[("tags", serde_json::to_string(batch)?)])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<SiblingsAndParentsResponse>()
+                    .await?
+                    .tags,
+            );
+        }
+        Ok(relations)
+    }
+
+    async fn tag_resolver(&self, tags: &[String], tag_service_key: &str) -> Result<TagResolver> {
+        let relations = self.get_siblings_and_parents(tags).await?;
+        Ok(TagResolver::new(tag_service_key, &relations))
+    }
+
+    async fn get_favourite_tags(&self) -> Result<Vec<String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/get_favourite_tags");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<Vec<String>>>()
+            .await?
+            .body)
+    }
+
+    async fn set_favourite_tags(&self, change: FavouriteTagsChange) -> Result<Vec<String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/set_favourite_tags");
+
+        Ok(self
+            .set_post_request_key(&req_url)?
+            .json(&change)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<Vec<String>>>()
+            .await?
+            .body)
+    }
+
+    async fn add_tags(
+        &self,
+        file: HydrusFile,
+        actions: HashMap<String, Vec<TagAction>>,
+        override_previously_deleted_mappings: Option<bool>,
+        create_new_deleted_mappings: Option<bool>,
+    ) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/add_tags");
+
+        let form = AddTagsRequest::new(
+            file,
+            &actions,
+            override_previously_deleted_mappings,
+            create_new_deleted_mappings,
+        );
+
+        self.set_post_request_key(&req_url)?
+            .json(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        // tag counts changed, so cached searches are stale
+        if let Some(cache) = &self.tag_search_cache {
+            cache.clear();
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EditingFileNotes for HydrusClient {
+    async fn set_notes(
+        &self,
+        file: SingleFile,
+        notes: HashMap<String, String>,
+        merge: NoteMerge,
+    ) -> Result<HashMap<String, String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_notes/set_notes");
+
+        let form = SetNotesRequest::new(file, notes, merge);
+
+        Ok(self
+            .set_post_request_key(&req_url)?
+            .json(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<HashMap<String, String>>>()
+            .await?
+            .body)
+    }
+
+    async fn delete_notes(&self, file: SingleFile, note_names: Vec<String>) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_notes/delete_notes");
+
+        let form = DeleteNotesRequest { file, note_names };
+
+        self.set_post_request_key(&req_url)?
+            .json(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EditingFileRatings for HydrusClient {
+    async fn set_rating(
+        &self,
+        file: SingleFile,
+        service: &Service,
+        rating: RatingValue,
+    ) -> Result<()> {
+        rating.validate(service)?;
+
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("edit_ratings/set_rating");
+
+        let form = SetRatingRequest {
+            file,
+            rating_service_key: service.service_key.to_owned(),
+            rating,
+        };
+
+        self.set_post_request_key(&req_url)?
+            .json(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EditingFileTimes for HydrusClient {
+    async fn set_time(
+        &self,
+        file: SingleFile,
+        timestamp_type: TimestampType,
+        timestamp_ms: Option<u64>,
+    ) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("edit_times/set_time");
+
+        let form = SetTimeRequest::new(file, timestamp_type, timestamp_ms);
+
+        self.set_post_request_key(&req_url)?
+            .json(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn increment_file_viewtime(
+        &self,
+        file: SingleFile,
+        canvas_type: CanvasType,
+        viewtime: f64,
+        views: Option<u64>,
+        timestamp_ms: Option<u64>,
+    ) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("edit_times/increment_file_viewtime");
+
+        let form = IncrementViewtimeRequest {
+            file,
+            canvas_type,
+            viewtime,
+            views,
+            timestamp_ms,
+        };
+
+        self.set_post_request_key(&req_url)?
+            .json(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ManagingFileRelationships for HydrusClient {
+    async fn get_file_relationships(
+        &self,
+        file: HydrusFile,
+        domain: Option<FileDomain>,
+    ) -> Result<HashMap<String, FileRelationships>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/get_file_relationships");
+
+        let mut request = self
+            .set_get_request_key(&req_url)?
+            .query(&[file.query_param()]);
+        if let Some(domain) = domain {
+            request = request.query(&[domain.query_param()]);
+        }
+
+        Ok(request
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<HashMap<String, FileRelationships>>>()
+            .await?
+            .body)
+    }
+
+    async fn get_potentials_count(&self, search: &PotentialsSearch) -> Result<u64> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/get_potentials_count");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query(&search.query_params()?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<u64>>()
+            .await?
+            .body)
+    }
+
+    async fn get_potential_pairs(
+        &self,
+        search: &PotentialsSearch,
+        max_num_pairs: Option<u64>,
+    ) -> Result<Vec<(Hash, Hash)>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/get_potential_pairs");
+
+        let mut request = self
+            .set_get_request_key(&req_url)?
+            .query(&search.query_params()?);
+        if let Some(max_num_pairs) = max_num_pairs {
+            request = request.query(&[("max_num_pairs", max_num_pairs)]);
+        }
+
+        Ok(request
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<Vec<(Hash, Hash)>>>()
+            .await?
+            .body)
+    }
+
+    async fn get_random_potentials(&self, search: &PotentialsSearch) -> Result<Vec<Hash>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/get_random_potentials");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query(&search.query_params()?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HydrusResponse<Vec<Hash>>>()
+            .await?
+            .body)
+    }
+
+    async fn set_file_relationships(&self, relationships: &[RelationshipDecision]) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/set_file_relationships");
+
+        let form = SetRelationshipsRequest { relationships };
+
+        self.set_post_request_key(&req_url)?
+            .json(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn set_kings(&self, file: HydrusFile) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/set_kings");
+
+        self.set_post_request_key(&req_url)?
+            .json(&file)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn remove_potentials(&self, file: HydrusFile) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/remove_potentials");
+
+        self.set_post_request_key(&req_url)?
+            .json(&file)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// file access which reads files straight from the client's disk when the key may see local
+/// paths, and downloads them otherwise
+pub struct LocalFileAccess<'a> {
+    client: &'a HydrusClient,
+    local: bool,
+}
+
+impl<'a> LocalFileAccess<'a> {
+    /// check the client's key for the `SeeLocalPaths` permission
+    pub async fn new(client: &'a HydrusClient) -> Result<LocalFileAccess<'a>> {
+        let key = client
+            .sessionkey
+            .as_ref()
+            .or(client.apikey.as_ref())
+            .ok_or(HydrusError::KeyNotSupplied)?;
+        let info = client.verify_access_key(key).await?;
+        let local = info.permits_everything
+            || info
+                .basic_permissions
+                .contains(&HydrusPermissions::SeeLocalPaths);
+
+        Ok(LocalFileAccess { client, local })
+    }
+
+    /// whether files are read from disk
+    pub fn is_local(&self) -> bool {
+        self.local
+    }
+
+    /// open a file. files the client's paths do not lead to, such as when it runs on another
+    /// machine, are downloaded
+    pub async fn open(&self, file: SingleFile) -> Result<FileDownload<ByteStream>> {
+        if self.local
+            && let Ok(location) = self.client.file_path(file.clone()).await
+            && let Ok(local_file) = tokio::fs::File::open(&location.path).await
+        {
+            let stream =
+                FramedRead::new(local_file, BytesCodec::new()).map_ok(|bytes| bytes.freeze());
+            return Ok(FileDownload {
+                info: DownloadInfo {
+                    mime: Some(location.filetype),
+                    size: Some(location.size),
+                    ..Default::default()
+                },
+                body: Box::pin(stream),
+            });
+        }
+
+        self.client.get_file(file, false).await
+    }
+}
+
+impl HydrusClient {
+    /// walk potential duplicate pairs and resolve them by `rules`, a page of pairs at a time. in
+    /// dry-run mode the decisions for the first page are only reported
+    pub async fn resolve_duplicates(&self, rules: &DuplicateRules) -> Result<ResolutionReport> {
+        let mut run = ResolutionRun::new(rules);
+        while !run.is_done() {
+            let page = self
+                .get_potential_pairs(&rules.search, rules.max_pairs)
+                .await?;
+            let pairs = run.new_pairs(page);
+            if pairs.is_empty() {
+                break;
+            }
+
+            let mut metadata = Vec::new();
+            for batch in ResolutionRun::metadata_batches(&pairs) {
+                metadata.extend(
+                    self.file_metadata(batch, FileMetadataOptions::default())
+                        .await?,
+                );
+            }
+
+            let decisions = run.plan(&pairs, metadata);
+            if !decisions.is_empty() {
+                self.set_file_relationships(&decisions).await?;
+            }
+        }
+        Ok(run.finish())
+    }
 }
@@ -0,0 +1,374 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, Write},
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::types::tags::{TagCount, TagDisplayType};
+
+/// extension of thumbnails still being written
+const TEMP_EXTENSION: &str = "tmp";
+
+/// on-disk thumbnail cache. thumbnails are stored by sha256 and the least recently used ones
+/// are evicted once the cache grows past its maximum size
+#[derive(Debug)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    max_size: u64,
+    /// total size of the cached thumbnails, counted on first use
+    size: Mutex<Option<u64>>,
+}
+
+impl ThumbnailCache {
+    /// create a cache in `dir` holding at most `max_size` bytes of thumbnails
+    pub fn new(dir: PathBuf, max_size: u64) -> io::Result<ThumbnailCache> {
+        fs::create_dir_all(&dir)?;
+        Ok(ThumbnailCache {
+            dir,
+            max_size,
+            size: Mutex::new(None),
+        })
+    }
+
+    fn path(&self, hash: &str) -> Option<PathBuf> {
+        if hash.len() < 2 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let hash = hash.to_ascii_lowercase();
+        Some(self.dir.join(&hash[..2]).join(hash))
+    }
+
+    /// get a cached thumbnail
+    pub fn get(&self, hash: &str) -> Option<Vec<u8>> {
+        let path = self.path(hash)?;
+        let bytes = fs::read(&path).ok()?;
+        // the modified time doubles as the last access time for eviction
+        if let Ok(file) = File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(bytes)
+    }
+
+    /// store a thumbnail, evicting old thumbnails if needed
+    pub fn insert(&self, hash: &str, bytes: &[u8]) -> io::Result<()> {
+        let path = self.path(hash).ok_or(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "thumbnails are cached by their hex hash",
+        ))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let previous = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+
+        // write and rename, so readers never see a partial thumbnail. the temporary name is
+        // unique, so concurrent inserts of a thumbnail do not write to the same file
+        static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+        let temp = path.with_extension(format!(
+            "{}.{}.{TEMP_EXTENSION}",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        File::create(&temp)?.write_all(bytes)?;
+        fs::rename(&temp, &path)?;
+
+        let mut size = self
+            .size
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        let total = match *size {
+            Some(total) => total + bytes.len() as u64 - previous.min(total),
+            None => self.entries()?.iter().map(|entry| entry.1).sum(),
+        };
+        *size = Some(if total > self.max_size {
+            self.evict(total)?
+        } else {
+            total
+        });
+        Ok(())
+    }
+
+    /// all cached thumbnails with their size and last access time
+    fn entries(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut entries = Vec::new();
+        for prefix in fs::read_dir(&self.dir)? {
+            let prefix = prefix?;
+            if !prefix.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(prefix.path())? {
+                let entry = entry?;
+                // thumbnails still being written are not part of the cache yet
+                if entry
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext == TEMP_EXTENSION)
+                {
+                    continue;
+                }
+                let metadata = entry.metadata()?;
+                if metadata.is_file() {
+                    entries.push((entry.path(), metadata.len(), metadata.modified()?));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// remove least recently used thumbnails until the cache fits, returns the new size
+    fn evict(&self, mut total: u64) -> io::Result<u64> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|entry| entry.2);
+        for (path, size, _) in entries {
+            if total <= self.max_size {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size.min(total);
+        }
+        Ok(total)
+    }
+}
+
+/// small in-memory cache of recent query results. the oldest entry is dropped once the cache is
+/// full, and entries expire after `max_age`
+#[derive(Debug)]
+pub(crate) struct QueryCache<K, V> {
+    entries: Mutex<VecDeque<(K, Instant, V)>>,
+    capacity: usize,
+    max_age: Duration,
+}
+
+impl<K: PartialEq, V: Clone> QueryCache<K, V> {
+    pub(crate) fn new(capacity: usize, max_age: Duration) -> QueryCache<K, V> {
+        QueryCache {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            max_age,
+        }
+    }
+
+    pub(crate) fn get(&self, key: &K) -> Option<V> {
+        self.find(|entry| entry == key)
+    }
+
+    /// value of the newest entry whose key matches
+    pub(crate) fn find(&self, matches: impl Fn(&K) -> bool) -> Option<V> {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        entries.retain(|entry| entry.1.elapsed() < self.max_age);
+        entries
+            .iter()
+            .rev()
+            .find(|entry| matches(&entry.0))
+            .map(|entry| entry.2.clone())
+    }
+
+    pub(crate) fn insert(&self, key: K, value: V) {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        entries.retain(|entry| entry.0 != key);
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back((key, Instant::now(), value));
+    }
+
+    pub(crate) fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .clear();
+    }
+}
+
+/// a tag search, by query, tag service, file domain and display type
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TagSearch {
+    pub(crate) search: String,
+    pub(crate) tag_service_key: Option<String>,
+    pub(crate) domain: Option<(&'static str, String)>,
+    pub(crate) display_type: TagDisplayType,
+}
+
+impl TagSearch {
+    /// whether this search only narrows `other`, so its tags are among the tags found by `other`
+    fn narrows(&self, other: &TagSearch) -> bool {
+        self.tag_service_key == other.tag_service_key
+            && self.domain == other.domain
+            && self.display_type == other.display_type
+            && !other.search.is_empty()
+            && self.search.starts_with(&other.search)
+            && !self.search.contains('*')
+    }
+
+    /// whether a tag would be found by this search: the tag, its subtag or a word of its subtag
+    /// starts with the search
+    fn matches(&self, tag: &str) -> bool {
+        if tag.starts_with(&self.search) {
+            return true;
+        }
+        let (namespace, search) = match self.search.split_once(':') {
+            Some((namespace, search)) => (Some(namespace), search),
+            None => (None, self.search.as_str()),
+        };
+        let (tag_namespace, subtag) = match tag.split_once(':') {
+            Some((tag_namespace, subtag)) => (Some(tag_namespace), subtag),
+            None => (None, tag),
+        };
+        namespace.is_none_or(|namespace| tag_namespace == Some(namespace))
+            && (subtag.starts_with(search)
+                || subtag
+                    .split_whitespace()
+                    .any(|word| word.starts_with(search)))
+    }
+}
+
+/// in-memory cache of tag searches, for autocomplete while a user types. a search extending a
+/// cached one, such as `samu` after `sam`, is answered by filtering the cached tags. this relies
+/// on the client returning every tag matching a search, which clients set to only autocomplete
+/// longer searches do not
+#[derive(Debug)]
+pub struct TagSearchCache {
+    searches: QueryCache<TagSearch, Vec<TagCount>>,
+}
+
+impl TagSearchCache {
+    /// create a cache of the last `capacity` searches, kept for `max_age`
+    pub fn new(capacity: usize, max_age: Duration) -> TagSearchCache {
+        TagSearchCache {
+            searches: QueryCache::new(capacity, max_age),
+        }
+    }
+
+    pub(crate) fn get(&self, search: &TagSearch) -> Option<Vec<TagCount>> {
+        if let Some(tags) = self.searches.get(search) {
+            return Some(tags);
+        }
+        let tags = self.searches.find(|cached| search.narrows(cached))?;
+        Some(
+            tags.into_iter()
+                .filter(|tag| search.matches(&tag.tag))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn insert(&self, search: TagSearch, tags: Vec<TagCount>) {
+        self.searches.insert(search, tags)
+    }
+
+    /// forget all searches, such as after tags changed
+    pub fn clear(&self) {
+        self.searches.clear()
+    }
+}
+
+/// guess the mime of a thumbnail from its first bytes
+pub(crate) fn thumbnail_mime(bytes: &[u8]) -> Option<String> {
+    let mime = if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        "image/jpeg"
+    } else if bytes.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        "image/webp"
+    } else {
+        return None;
+    };
+    Some(mime.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let dir = std::env::temp_dir().join(format!("hydrus_api_cache_{}", std::process::id()));
+        let cache = ThumbnailCache::new(dir.clone(), 10).unwrap();
+        // leftovers of interrupted inserts do not count towards the size
+        fs::create_dir_all(dir.join("aa")).unwrap();
+        fs::write(dir.join("aa").join("aa02.1.0.tmp"), [0; 8]).unwrap();
+        cache.insert("aa01", &[0; 4]).unwrap();
+        cache.insert("bb02", &[1; 4]).unwrap();
+        File::options()
+            .append(true)
+            .open(dir.join("bb").join("bb02"))
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        assert_eq!(cache.get("AA01"), Some(vec![0; 4]));
+        cache.insert("cc03", &[2; 4]).unwrap();
+        assert_eq!(cache.get("bb02"), None);
+        assert!(cache.get("aa01").is_some() && cache.get("cc03").is_some());
+        assert_eq!(cache.get("../etc"), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn narrows_cached_tag_searches() {
+        let search = |text: &str| TagSearch {
+            search: text.to_string(),
+            tag_service_key: None,
+            domain: None,
+            display_type: TagDisplayType::Storage,
+        };
+        let tag = |tag: &str| TagCount {
+            tag: tag.to_string(),
+            count: 1,
+        };
+        let cache = TagSearchCache::new(8, Duration::from_secs(60));
+        cache.insert(
+            search("sa"),
+            vec![
+                tag("samus aran"),
+                tag("sakura"),
+                tag("character:samus aran"),
+                tag("character:tall sam"),
+                tag("series:sam and max"),
+            ],
+        );
+
+        assert_eq!(cache.get(&search("sa")).unwrap().len(), 5);
+        assert_eq!(
+            cache.get(&search("sam")).unwrap(),
+            [
+                tag("samus aran"),
+                tag("character:samus aran"),
+                tag("character:tall sam"),
+                tag("series:sam and max")
+            ]
+        );
+        assert_eq!(cache.get(&search("character:sam")), None);
+        assert_eq!(cache.get(&search("s")), None);
+        assert_eq!(cache.get(&search("sa*s")), None);
+        let other_service = TagSearch {
+            tag_service_key: Some("ab".to_string()),
+            ..search("sam")
+        };
+        assert_eq!(cache.get(&other_service), None);
+
+        cache.clear();
+        assert_eq!(cache.get(&search("sa")), None);
+    }
+
+    #[test]
+    fn query_cache_drops_oldest_and_expired() {
+        let cache = QueryCache::new(2, Duration::from_millis(50));
+        cache.insert("sa", 1);
+        cache.insert("sam", 2);
+        cache.insert("samu", 3);
+        assert_eq!(cache.get(&"sa"), None);
+        assert_eq!(cache.get(&"samu"), Some(3));
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get(&"sam"), None);
+    }
+}
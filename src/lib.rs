@@ -3,6 +3,10 @@
 /// async traits and client implementation
 #[cfg(feature = "async")]
 pub mod async_lib;
+/// on-disk caches for data which never changes on the client
+pub mod cache;
+/// rules for resolving potential duplicates automatically
+pub mod rules;
 /// sync traits and client implementation
 #[cfg(feature = "sync")]
 pub mod sync_lib;
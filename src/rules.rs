@@ -0,0 +1,546 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::types::{
+    FileMetadata, Hash, HydrusFile,
+    duplicates::{PixelDuplicates, PotentialsSearch, Relationship, RelationshipDecision},
+};
+
+/// files whose metadata is loaded per request while resolving duplicates
+const METADATA_BATCH_SIZE: usize = 100;
+
+/// compares the two files of a potential duplicate pair
+pub trait Comparator: Send + Sync {
+    /// name shown in reports
+    fn name(&self) -> String;
+    /// `Greater` if `a` is the better file, `Equal` if the comparator has no preference
+    fn compare(&self, a: &FileMetadata, b: &FileMetadata) -> Ordering;
+}
+
+/// prefers the file with more pixels
+pub struct HigherResolution;
+
+impl Comparator for HigherResolution {
+    fn name(&self) -> String {
+        "higher resolution".to_string()
+    }
+
+    fn compare(&self, a: &FileMetadata, b: &FileMetadata) -> Ordering {
+        let pixels = |file: &FileMetadata| Some(file.width? as u64 * file.height? as u64);
+        match (pixels(a), pixels(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// prefers the larger file
+pub struct LargerFilesize;
+
+impl Comparator for LargerFilesize {
+    fn name(&self) -> String {
+        "larger filesize".to_string()
+    }
+
+    fn compare(&self, a: &FileMetadata, b: &FileMetadata) -> Ordering {
+        match (a.size, b.size) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// prefers filetypes by their mime, earlier ones first. files of other filetypes lose to listed
+/// ones
+pub struct PreferredFiletype(pub Vec<String>);
+
+impl Comparator for PreferredFiletype {
+    fn name(&self) -> String {
+        format!("preferred filetype ({})", self.0.join(", "))
+    }
+
+    fn compare(&self, a: &FileMetadata, b: &FileMetadata) -> Ordering {
+        let rank = |file: &FileMetadata| {
+            file.mime
+                .as_ref()
+                .and_then(|mime| self.0.iter().position(|preferred| preferred == mime))
+                .unwrap_or(self.0.len())
+        };
+        // a lower rank is better
+        rank(b).cmp(&rank(a))
+    }
+}
+
+/// prefers the file with more current tags on a tag service, as displayed
+pub struct MoreTags {
+    pub tag_service_key: String,
+}
+
+impl Comparator for MoreTags {
+    fn name(&self) -> String {
+        "more tags".to_string()
+    }
+
+    fn compare(&self, a: &FileMetadata, b: &FileMetadata) -> Ordering {
+        let count = |file: &FileMetadata| {
+            file.tags
+                .get(&self.tag_service_key)
+                .map_or(0, |tags| tags.display_tags.current.len())
+        };
+        count(a).cmp(&count(b))
+    }
+}
+
+/// rules for resolving potential duplicate pairs automatically. a pair is resolved when at least
+/// one comparator prefers a file and no comparator prefers the other.
+///
+/// the client pairs the kings of duplicate groups. a file which loses a decision joins the
+/// winner's group and is no longer a king, so later pairs with it are skipped. kings changed
+/// outside of a run while it goes are not re-checked
+pub struct DuplicateRules {
+    /// pairs to walk
+    pub search: PotentialsSearch,
+    pub comparators: Vec<Box<dyn Comparator>>,
+    /// pairs fetched per request, the client's default if not given. pages are fetched until
+    /// one brings no decisions
+    pub max_pairs: Option<u64>,
+    /// decisions made per run
+    pub max_decisions: Option<usize>,
+    pub do_default_content_merge: bool,
+    /// delete the worse file of each resolved pair
+    pub delete_worse: bool,
+    /// only report decisions, without applying them
+    pub dry_run: bool,
+}
+
+impl DuplicateRules {
+    /// rules for pixel duplicates, starting in dry-run mode
+    pub fn new(comparators: Vec<Box<dyn Comparator>>) -> DuplicateRules {
+        DuplicateRules {
+            search: PotentialsSearch {
+                pixel_duplicates: PixelDuplicates::Required,
+                ..Default::default()
+            },
+            comparators,
+            max_pairs: None,
+            max_decisions: None,
+            do_default_content_merge: true,
+            delete_worse: false,
+            dry_run: true,
+        }
+    }
+
+    /// decide pairs by the comparators, without contacting the client
+    pub fn plan(
+        &self,
+        pairs: &[(Hash, Hash)],
+        metadata: &HashMap<Hash, FileMetadata>,
+    ) -> ResolutionReport {
+        let mut report = ResolutionReport::default();
+        self.plan_into(&mut report, pairs, metadata);
+        report
+    }
+
+    /// decide pairs, continuing a report of earlier pairs of the run. returns the number of new
+    /// decisions
+    fn plan_into(
+        &self,
+        report: &mut ResolutionReport,
+        pairs: &[(Hash, Hash)],
+        metadata: &HashMap<Hash, FileMetadata>,
+    ) -> usize {
+        let decided = report.resolutions.len();
+        // files which lost a decision are no longer kings
+        let mut worse: HashSet<Hash> = report
+            .resolutions
+            .iter()
+            .map(|resolution| resolution.decision.hash_b.to_owned())
+            .collect();
+
+        for (hash_a, hash_b) in pairs {
+            let skip = |reason| SkippedPair {
+                hash_a: hash_a.to_owned(),
+                hash_b: hash_b.to_owned(),
+                reason,
+            };
+
+            if worse.contains(hash_a) || worse.contains(hash_b) {
+                report.skipped.push(skip(if self.delete_worse {
+                    SkipReason::AlreadyDeleted
+                } else {
+                    SkipReason::NotKing
+                }));
+                continue;
+            }
+            let (Some(a), Some(b)) = (metadata.get(hash_a), metadata.get(hash_b)) else {
+                report.skipped.push(skip(SkipReason::MissingMetadata));
+                continue;
+            };
+            let (ordering, comparators) = match self.judge(a, b) {
+                Ok(verdict) => verdict,
+                Err(reason) => {
+                    report.skipped.push(skip(reason));
+                    continue;
+                }
+            };
+            if self.cap_reached(report) {
+                report.skipped.push(skip(SkipReason::DecisionCap));
+                continue;
+            }
+
+            let (better, worse_file) = match ordering {
+                Ordering::Less => (hash_b, hash_a),
+                _ => (hash_a, hash_b),
+            };
+            worse.insert(worse_file.to_owned());
+            report.resolutions.push(Resolution {
+                decision: RelationshipDecision {
+                    hash_a: better.to_owned(),
+                    hash_b: worse_file.to_owned(),
+                    relationship: Relationship::ABetter,
+                    do_default_content_merge: self.do_default_content_merge,
+                    delete_a: false,
+                    delete_b: self.delete_worse,
+                },
+                comparators,
+            });
+        }
+
+        report.resolutions.len() - decided
+    }
+
+    fn cap_reached(&self, report: &ResolutionReport) -> bool {
+        self.max_decisions
+            .is_some_and(|max| report.resolutions.len() >= max)
+    }
+
+    /// which file is better, with the comparators preferring it
+    fn judge(
+        &self,
+        a: &FileMetadata,
+        b: &FileMetadata,
+    ) -> Result<(Ordering, Vec<String>), SkipReason> {
+        let mut verdict = Ordering::Equal;
+        let mut comparators = Vec::new();
+        for comparator in &self.comparators {
+            match comparator.compare(a, b) {
+                Ordering::Equal => {}
+                ordering if verdict == Ordering::Equal || verdict == ordering => {
+                    verdict = ordering;
+                    comparators.push(comparator.name());
+                }
+                _ => return Err(SkipReason::Conflict),
+            }
+        }
+        match verdict {
+            Ordering::Equal => Err(SkipReason::NoPreference),
+            _ => Ok((verdict, comparators)),
+        }
+    }
+}
+
+/// a resolved pair, with the comparators preferring the better file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    /// file a is the better file
+    pub decision: RelationshipDecision,
+    pub comparators: Vec<String>,
+}
+
+/// why a pair was left unresolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// the client returned no metadata for a file
+    MissingMetadata,
+    /// no comparator preferred either file
+    NoPreference,
+    /// comparators preferred different files
+    Conflict,
+    /// a file is deleted by an earlier decision of the run
+    AlreadyDeleted,
+    /// a file lost an earlier decision of the run, so it is no longer its group's king
+    NotKing,
+    /// the run made its maximum number of decisions
+    DecisionCap,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SkipReason::MissingMetadata => "missing metadata",
+            SkipReason::NoPreference => "no preference",
+            SkipReason::Conflict => "conflicting comparators",
+            SkipReason::AlreadyDeleted => "file already deleted",
+            SkipReason::NotKing => "file no longer king",
+            SkipReason::DecisionCap => "decision cap reached",
+        })
+    }
+}
+
+/// an unresolved pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedPair {
+    pub hash_a: Hash,
+    pub hash_b: Hash,
+    pub reason: SkipReason,
+}
+
+/// state of a run of duplicate rules against a client, which fetches pages of pairs, loads
+/// their metadata and applies the decisions
+pub(crate) struct ResolutionRun<'a> {
+    rules: &'a DuplicateRules,
+    report: ResolutionReport,
+    seen: HashSet<(Hash, Hash)>,
+    done: bool,
+}
+
+impl<'a> ResolutionRun<'a> {
+    pub(crate) fn new(rules: &'a DuplicateRules) -> ResolutionRun<'a> {
+        ResolutionRun {
+            rules,
+            report: ResolutionReport::default(),
+            seen: HashSet::new(),
+            done: false,
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.done || self.rules.cap_reached(&self.report)
+    }
+
+    /// pairs of a page not decided or skipped earlier in the run. the client keeps returning
+    /// skipped pairs, so a page without new pairs ends the run
+    pub(crate) fn new_pairs(&mut self, page: Vec<(Hash, Hash)>) -> Vec<(Hash, Hash)> {
+        let pairs: Vec<_> = page
+            .into_iter()
+            .filter(|pair| self.seen.insert(pair.to_owned()))
+            .collect();
+        if pairs.is_empty() {
+            self.done = true;
+        }
+        pairs
+    }
+
+    /// files of the pairs, in batches for loading their metadata
+    pub(crate) fn metadata_batches(pairs: &[(Hash, Hash)]) -> Vec<HydrusFile> {
+        let mut hashes: Vec<Hash> = pairs
+            .iter()
+            .flat_map(|(a, b)| [a.to_owned(), b.to_owned()])
+            .collect();
+        hashes.sort();
+        hashes.dedup();
+        hashes
+            .chunks(METADATA_BATCH_SIZE)
+            .map(|batch| HydrusFile::Hashes(batch.to_vec()))
+            .collect()
+    }
+
+    /// decide a page of pairs, returning the decisions to send to the client. without any, the
+    /// client's pairs stay as they are and the next page would repeat this one, so the run ends
+    pub(crate) fn plan(
+        &mut self,
+        pairs: &[(Hash, Hash)],
+        metadata: Vec<FileMetadata>,
+    ) -> Vec<RelationshipDecision> {
+        // unknown files come back without a file id and count as missing
+        let metadata: HashMap<Hash, FileMetadata> = metadata
+            .into_iter()
+            .filter(|file| file.file_id.is_some())
+            .map(|file| (file.hash.to_owned(), file))
+            .collect();
+        let decided = self.rules.plan_into(&mut self.report, pairs, &metadata);
+        if self.rules.dry_run || decided == 0 {
+            self.done = true;
+            return Vec::new();
+        }
+        self.report.applied = true;
+        self.report.resolutions[self.report.resolutions.len() - decided..]
+            .iter()
+            .map(|resolution| resolution.decision.to_owned())
+            .collect()
+    }
+
+    pub(crate) fn finish(self) -> ResolutionReport {
+        self.report
+    }
+}
+
+/// outcome of a run of duplicate rules
+#[derive(Debug, Clone, Default)]
+pub struct ResolutionReport {
+    pub resolutions: Vec<Resolution>,
+    pub skipped: Vec<SkippedPair>,
+    /// whether the decisions were sent to the client
+    pub applied: bool,
+}
+
+impl fmt::Display for ResolutionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for resolution in &self.resolutions {
+            let decision = &resolution.decision;
+            write!(f, "{} is better than {}", decision.hash_a, decision.hash_b)?;
+            if decision.delete_b {
+                write!(f, ", deleting the worse file")?;
+            }
+            writeln!(f, " ({})", resolution.comparators.join(", "))?;
+        }
+        for skipped in &self.skipped {
+            writeln!(
+                f,
+                "skipped {} and {}: {}",
+                skipped.hash_a, skipped.hash_b, skipped.reason
+            )?;
+        }
+        write!(
+            f,
+            "{} resolved, {} skipped, {}",
+            self.resolutions.len(),
+            self.skipped.len(),
+            if self.applied { "applied" } else { "dry run" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(hash: &str, width: u32, size: u64, mime: &str) -> (Hash, FileMetadata) {
+        let metadata = serde_json::from_value(serde_json::json!({
+            "file_id": 1,
+            "hash": hash,
+            "width": width,
+            "height": width,
+            "size": size,
+            "mime": mime,
+        }))
+        .unwrap();
+        (hash.to_string(), metadata)
+    }
+
+    fn rules() -> DuplicateRules {
+        let mut rules = DuplicateRules::new(vec![
+            Box::new(HigherResolution),
+            Box::new(PreferredFiletype(vec!["image/png".to_string()])),
+            Box::new(LargerFilesize),
+        ]);
+        rules.delete_worse = true;
+        rules
+    }
+
+    #[test]
+    fn resolves_agreeing_pairs_and_skips_conflicts() {
+        let metadata = HashMap::from([
+            file("a", 100, 500, "image/jpeg"),
+            file("b", 200, 900, "image/jpeg"),
+            file("c", 300, 100, "image/jpeg"),
+            file("d", 200, 900, "image/jpeg"),
+        ]);
+        let pairs = [
+            ("a".to_string(), "b".to_string()),
+            ("b".to_string(), "c".to_string()),
+            ("b".to_string(), "d".to_string()),
+            ("a".to_string(), "e".to_string()),
+        ];
+        let report = rules().plan(&pairs, &metadata);
+
+        assert_eq!(report.resolutions.len(), 1);
+        let decision = &report.resolutions[0].decision;
+        assert_eq!(
+            (decision.hash_a.as_str(), decision.hash_b.as_str()),
+            ("b", "a")
+        );
+        assert!(decision.delete_b && !decision.delete_a);
+        assert_eq!(
+            report.resolutions[0].comparators,
+            ["higher resolution", "larger filesize"]
+        );
+        let reasons: Vec<_> = report
+            .skipped
+            .iter()
+            .map(|skipped| skipped.reason)
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                SkipReason::Conflict,
+                SkipReason::NoPreference,
+                SkipReason::AlreadyDeleted
+            ]
+        );
+    }
+
+    #[test]
+    fn caps_decisions() {
+        let metadata = HashMap::from([
+            file("a", 100, 100, "image/jpeg"),
+            file("b", 200, 100, "image/png"),
+            file("c", 100, 100, "image/jpeg"),
+            file("d", 200, 100, "image/png"),
+        ]);
+        let pairs = [
+            ("a".to_string(), "b".to_string()),
+            ("c".to_string(), "d".to_string()),
+        ];
+        let mut rules = rules();
+        rules.max_decisions = Some(1);
+        let report = rules.plan(&pairs, &metadata);
+
+        assert_eq!(report.resolutions.len(), 1);
+        assert_eq!(report.skipped[0].reason, SkipReason::DecisionCap);
+        assert!(
+            report
+                .to_string()
+                .ends_with("1 resolved, 1 skipped, dry run")
+        );
+    }
+
+    #[test]
+    fn runs_pages_until_nothing_is_decided() {
+        let metadata = || {
+            [
+                file("a", 100, 100, "image/jpeg"),
+                file("b", 200, 100, "image/jpeg"),
+                file("c", 300, 100, "image/jpeg"),
+            ]
+            .into_iter()
+            .map(|(_, metadata)| metadata)
+            .collect::<Vec<_>>()
+        };
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        let mut rules = rules();
+        rules.delete_worse = false;
+        rules.dry_run = false;
+        let mut run = ResolutionRun::new(&rules);
+
+        let pairs = run.new_pairs(vec![pair("a", "b"), pair("a", "c")]);
+        let decisions = run.plan(&pairs, metadata());
+        assert_eq!(decisions.len(), 1);
+        assert!(!run.is_done());
+
+        // the client moved the pair to the new king, and still returns the skipped one
+        let pairs = run.new_pairs(vec![pair("a", "c"), pair("b", "c")]);
+        assert_eq!(pairs, [pair("b", "c")]);
+        let decisions = run.plan(&pairs, metadata());
+        assert_eq!(
+            (decisions[0].hash_a.as_str(), decisions[0].hash_b.as_str()),
+            ("c", "b")
+        );
+
+        assert!(run.new_pairs(vec![pair("a", "c")]).is_empty());
+        assert!(run.is_done());
+        let report = run.finish();
+        assert_eq!(report.resolutions.len(), 2);
+        assert_eq!(report.skipped[0].reason, SkipReason::NotKing);
+        assert!(report.applied);
+
+        rules.dry_run = true;
+        let mut run = ResolutionRun::new(&rules);
+        let pairs = run.new_pairs(vec![pair("a", "b")]);
+        assert!(run.plan(&pairs, metadata()).is_empty());
+        assert!(run.is_done() && !run.finish().applied);
+    }
+}
@@ -1,12 +1,20 @@
+use crate::cache::{TagSearch, TagSearchCache, ThumbnailCache, thumbnail_mime};
+use crate::rules::{DuplicateRules, ResolutionReport, ResolutionRun};
 use crate::sync_lib::HydrusError;
 use crate::sync_lib::traits::*;
+use crate::types::duplicates::*;
+use crate::types::search::*;
+use crate::types::tags::*;
 use crate::types::*;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::fs::File;
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
 use ureq::{
     RequestBuilder,
+    http::header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE},
     typestate::{WithBody, WithoutBody},
 };
 
@@ -18,6 +26,8 @@ pub struct HydrusClient {
     apikey: Option<String>,
     sessionkey: Option<String>,
     url: String,
+    thumbnail_cache: Option<ThumbnailCache>,
+    tag_search_cache: Option<TagSearchCache>,
 }
 
 impl HydrusClient {
@@ -32,6 +42,8 @@ impl HydrusClient {
             apikey: None,
             sessionkey: None,
             url,
+            thumbnail_cache: None,
+            tag_search_cache: None,
         }
     }
     /// set an api key
@@ -42,6 +54,14 @@ impl HydrusClient {
     pub fn set_session_key(&mut self, key: String) {
         self.sessionkey = Some(key.to_owned())
     }
+    /// cache thumbnails requested by hash on disk
+    pub fn set_thumbnail_cache(&mut self, cache: ThumbnailCache) {
+        self.thumbnail_cache = Some(cache)
+    }
+    /// cache tag searches in memory, for autocomplete
+    pub fn set_tag_search_cache(&mut self, cache: TagSearchCache) {
+        self.tag_search_cache = Some(cache)
+    }
 
     fn set_get_request_key(&self, url: &str) -> Result<RequestBuilder<WithoutBody>> {
         let request = self.client.get(url);
@@ -64,6 +84,43 @@ impl HydrusClient {
             Err(HydrusError::KeyNotSupplied)
         }
     }
+
+    /// request a file, starting at `offset`
+    fn request_file(
+        &self,
+        file: &SingleFile,
+        download: bool,
+        offset: u64,
+    ) -> Result<FileDownload<FileReader>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/file");
+
+        let (name, value) = file.query_param();
+        let mut request = self
+            .set_get_request_key(&req_url)?
+            .query(name, value)
+            .query("download", download.to_string());
+
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={offset}-"));
+        }
+
+        let response = request.call()?;
+
+        let headers = response.headers();
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let info = DownloadInfo::from_headers(
+            header(CONTENT_TYPE),
+            header(CONTENT_LENGTH),
+            header(CONTENT_RANGE),
+            header(CONTENT_DISPOSITION),
+        );
+
+        Ok(FileDownload {
+            info,
+            body: Box::new(response.into_body().into_reader()),
+        })
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -72,7 +129,17 @@ struct HydrusResponse<T> {
         alias = "service",
         alias = "services",
         alias = "access_key",
-        alias = "session_key"
+        alias = "session_key",
+        alias = "metadata",
+        alias = "locations",
+        alias = "hashes",
+        alias = "tags",
+        alias = "favourite_tags",
+        alias = "notes",
+        alias = "file_relationships",
+        alias = "potential_duplicates_count",
+        alias = "potential_duplicate_pairs",
+        alias = "random_potential_duplicate_hashes"
     )]
     body: T,
 }
@@ -401,4 +468,811 @@ impl ImportingAndEditingUrls for HydrusClient {
 
         Ok(request.call()?.body_mut().read_json::<FilesUrlResponse>()?)
     }
+
+    fn add_url(&self, url: &str, options: AddUrlOptions) -> Result<AddUrlResponse> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_urls/add_url");
+
+        let form = AddUrlRequest::new(url, options);
+
+        Ok(self
+            .set_post_request_key(&req_url)?
+            .send_json(&form)?
+            .body_mut()
+            .read_json::<AddUrlResponse>()?)
+    }
+
+    fn get_url_info(&self, url: &str) -> Result<UrlInfo> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_urls/get_url_info");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query("url", url)
+            .call()?
+            .body_mut()
+            .read_json::<UrlInfo>()?)
+    }
+
+    fn associate_url(
+        &self,
+        file: HydrusFile,
+        urls_to_add: Vec<String>,
+        urls_to_delete: Vec<String>,
+        normalise_urls: Option<bool>,
+    ) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_urls/associate_url");
+
+        let form = AssociateUrlRequest {
+            file,
+            urls_to_add,
+            urls_to_delete,
+            normalise_urls,
+        };
+
+        let _ = self.set_post_request_key(&req_url)?.send_json(&form)?;
+
+        Ok(())
+    }
+}
+
+impl SearchingAndFetchingFiles for HydrusClient {
+    fn search_files(
+        &self,
+        tags: &[SearchTag],
+        domain: Option<FileDomain>,
+        tag_service_key: Option<String>,
+        sort_type: Option<FileSortType>,
+        sort_direction: Option<SortDirection>,
+        returns: SearchReturns,
+    ) -> Result<SearchFilesResponse> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/search_files");
+
+        let mut request = self.set_get_request_key(&req_url)?;
+
+        request = request.query("tags", serde_json::to_string(tags)?);
+
+        if let Some(file_domain) = domain {
+            let (name, value) = file_domain.query_param();
+            request = request.query(name, value);
+        }
+
+        if let Some(key) = tag_service_key {
+            request = request.query("tag_service_key", key);
+        }
+
+        if let Some(sort) = sort_type {
+            request = request.query("file_sort_type", (sort as u8).to_string());
+        }
+
+        if let Some(direction) = sort_direction {
+            request = request.query(
+                "file_sort_asc",
+                if direction == SortDirection::Ascending {
+                    "true"
+                } else {
+                    "false"
+                },
+            );
+        }
+
+        let (file_ids, hashes) = match returns {
+            SearchReturns::FileIds => ("true", "false"),
+            SearchReturns::Hashes => ("false", "true"),
+            SearchReturns::Both => ("true", "true"),
+        };
+        request = request
+            .query("return_file_ids", file_ids)
+            .query("return_hashes", hashes);
+
+        Ok(request
+            .call()?
+            .body_mut()
+            .read_json::<SearchFilesResponse>()?)
+    }
+
+    fn file_metadata(
+        &self,
+        file: HydrusFile,
+        options: FileMetadataOptions,
+    ) -> Result<Vec<FileMetadata>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/file_metadata");
+
+        let (name, value) = file.query_param();
+        let request = self
+            .set_get_request_key(&req_url)?
+            .query(name, value)
+            .query(
+                "only_return_identifiers",
+                options.only_return_identifiers.to_string(),
+            )
+            .query(
+                "only_return_basic_information",
+                options.only_return_basic_information.to_string(),
+            )
+            .query("include_notes", options.include_notes.to_string())
+            .query("include_blurhash", options.include_blurhash.to_string())
+            .query(
+                "detailed_url_information",
+                options.detailed_url_information.to_string(),
+            )
+            .query(
+                "include_milliseconds",
+                options.include_milliseconds.to_string(),
+            )
+            .query("include_services_object", "false");
+
+        Ok(request
+            .call()?
+            .body_mut()
+            .read_json::<HydrusResponse<Vec<FileMetadata>>>()?
+            .body)
+    }
+
+    fn get_file(&self, file: SingleFile, download: bool) -> Result<FileDownload<FileReader>> {
+        self.request_file(&file, download, 0)
+    }
+
+    fn render_file(&self, file: SingleFile, options: RenderOptions) -> Result<RenderedFile> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/render");
+
+        let (name, value) = file.query_param();
+        let response = self
+            .set_get_request_key(&req_url)?
+            .query(name, value)
+            .query_pairs(options.query_params())
+            .call()?;
+
+        let mime = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+            .or(options.format.map(|format| format.mime().to_string()))
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        // read_to_vec stops at 10 MB, which a full size render can pass. read through the body
+        // reader instead, as file downloads do, since it has no limit
+        let mut bytes = Vec::new();
+        response.into_body().into_reader().read_to_end(&mut bytes)?;
+
+        Ok(RenderedFile { mime, bytes })
+    }
+
+    fn download_to_path(
+        &self,
+        file: SingleFile,
+        path: PathBuf,
+        progress: Option<&(dyn Fn(DownloadProgress) + Send + Sync)>,
+    ) -> Result<PathBuf> {
+        let (path, first) = if path.is_dir() {
+            let download = self.request_file(&file, true, 0)?;
+            let name = download
+                .info
+                .filename
+                .as_deref()
+                .and_then(|name| Path::new(name).file_name())
+                .ok_or(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    "no file name suggested by the client",
+                ))?;
+            (path.join(name), Some(download))
+        } else {
+            (path, None)
+        };
+        let part = part_path(&path)?;
+        if path.exists() {
+            return Err(already_exists(&path).into());
+        }
+
+        let resume_from = std::fs::metadata(&part).map_or(0, |metadata| metadata.len());
+        let download = match first {
+            Some(download) if resume_from == 0 => Some(download),
+            _ => match self.request_file(&file, true, resume_from) {
+                Ok(download) => Some(download),
+                Err(HydrusError::NetworkError(ureq::Error::StatusCode(416))) if resume_from > 0 => {
+                    match file {
+                        // the part file may hold the whole file, which the hash check tells
+                        SingleFile::Hash(_) => None,
+                        // without a hash the part file cannot be checked, so start over
+                        SingleFile::FileId(_) => Some(self.request_file(&file, true, 0)?),
+                    }
+                }
+                Err(error) => return Err(error),
+            },
+        };
+
+        // the client may resume from an earlier position than asked for, or send the whole file
+        let start = download
+            .as_ref()
+            .map_or(resume_from, |download| download.info.offset);
+        if start > resume_from {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "the client resumed past the end of the partial file",
+            )
+            .into());
+        }
+
+        let mut output = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&part)?;
+        output.set_len(start)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut Read::by_ref(&mut output).take(start), &mut hasher)?;
+
+        if let Some(mut download) = download {
+            let mut done = start;
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                let read = download.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                output.write_all(&buffer[..read])?;
+                hasher.update(&buffer[..read]);
+                done += read as u64;
+                if let Some(progress) = progress {
+                    progress(DownloadProgress {
+                        done,
+                        total: download.info.size,
+                    });
+                }
+            }
+        }
+        output.flush()?;
+        drop(output);
+
+        if let SingleFile::Hash(expected) = &file {
+            let actual = format!("{:x}", hasher.finalize());
+            if !actual.eq_ignore_ascii_case(expected) {
+                // a broken part file would otherwise be resumed again
+                std::fs::remove_file(&part)?;
+                return Err(HydrusError::HashMismatch {
+                    expected: expected.to_owned(),
+                    actual,
+                });
+            }
+        }
+
+        if path.exists() {
+            return Err(already_exists(&path).into());
+        }
+        std::fs::rename(&part, &path)?;
+        Ok(path)
+    }
+
+    fn get_thumbnail(&self, file: SingleFile, blurhash_fallback: bool) -> Result<Thumbnail> {
+        let cache_key = match (&file, &self.thumbnail_cache) {
+            (SingleFile::Hash(hash), Some(cache)) => {
+                if let Some(bytes) = cache.get(hash) {
+                    return Ok(Thumbnail::Image {
+                        mime: thumbnail_mime(&bytes),
+                        bytes,
+                    });
+                }
+                Some(hash.to_owned())
+            }
+            _ => None,
+        };
+
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/thumbnail");
+
+        let (name, value) = file.query_param();
+        let mut response = match self
+            .set_get_request_key(&req_url)?
+            .query(name, value)
+            .call()
+        {
+            Ok(response) => response,
+            Err(error @ ureq::Error::StatusCode(_)) if blurhash_fallback => {
+                let options = FileMetadataOptions {
+                    include_blurhash: true,
+                    ..Default::default()
+                };
+                return match self
+                    .file_metadata(file.into(), options)?
+                    .into_iter()
+                    .next()
+                    .and_then(|metadata| metadata.blurhash)
+                {
+                    Some(blurhash) => Ok(Thumbnail::Blurhash(blurhash)),
+                    None => Err(error.into()),
+                };
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let mime = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        let bytes = response.body_mut().read_to_vec()?;
+
+        if let (Some(hash), Some(cache)) = (cache_key, &self.thumbnail_cache) {
+            // a failing cache should not fail the request
+            let _ = cache.insert(&hash, &bytes);
+        }
+
+        Ok(Thumbnail::Image { mime, bytes })
+    }
+
+    fn file_path(&self, file: SingleFile) -> Result<LocalFilePath> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/file_path");
+
+        let (name, value) = file.query_param();
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query(name, value)
+            .call()?
+            .body_mut()
+            .read_json::<LocalFilePath>()?)
+    }
+
+    fn thumbnail_path(&self, file: SingleFile) -> Result<LocalThumbnailPath> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/thumbnail_path");
+
+        let (name, value) = file.query_param();
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query(name, value)
+            .query("include_thumbnail_filetype", "true")
+            .call()?
+            .body_mut()
+            .read_json::<LocalThumbnailPath>()?)
+    }
+
+    fn local_file_storage_locations(&self) -> Result<Vec<StorageLocation>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/local_file_storage_locations");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .call()?
+            .body_mut()
+            .read_json::<HydrusResponse<Vec<StorageLocation>>>()?
+            .body)
+    }
+
+    fn file_hashes(
+        &self,
+        hashes: &[String],
+        source: HashType,
+        desired: HashType,
+    ) -> Result<HashMap<String, String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("get_files/file_hashes");
+
+        let mut converted = HashMap::new();
+        for batch in hashes.chunks(FILE_HASHES_BATCH_SIZE) {
+            converted.extend(
+                self.set_get_request_key(&req_url)?
+                    .query("hashes", serde_json::to_string(batch)?)
+                    .query("source_hash_type", source.as_str())
+                    .query("desired_hash_type", desired.as_str())
+                    .call()?
+                    .body_mut()
+                    .read_json::<HydrusResponse<HashMap<String, String>>>()?
+                    .body,
+            );
+        }
+        Ok(converted)
+    }
+}
+
+impl EditingFileTags for HydrusClient {
+    fn clean_tags(&self, tags: &[String]) -> Result<Vec<String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/clean_tags");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query("tags", serde_json::to_string(tags)?)
+            .call()?
+            .body_mut()
+            .read_json::<HydrusResponse<Vec<String>>>()?
+            .body)
+    }
+
+    fn search_tags(
+        &self,
+        search: &str,
+        tag_service_key: Option<String>,
+        domain: Option<FileDomain>,
+        display_type: TagDisplayType,
+    ) -> Result<Vec<TagCount>> {
+        let key = TagSearch {
+            search: search.to_string(),
+            tag_service_key,
+            domain: domain.map(|domain| domain.query_param()),
+            display_type,
+        };
+        if let Some(tags) = self
+            .tag_search_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&key))
+        {
+            return Ok(tags);
+        }
+
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/search_tags");
+
+        let mut request = self
+            .set_get_request_key(&req_url)?
+            .query("search", search)
+            .query("tag_display_type", display_type.as_str());
+        if let Some(tag_service_key) = &key.tag_service_key {
+            request = request.query("tag_service_key", tag_service_key);
+        }
+        if let Some((name, value)) = &key.domain {
+            request = request.query(*name, value);
+        }
+
+        let tags = request
+            .call()?
+            .body_mut()
+            .read_json::<HydrusResponse<Vec<TagCount>>>()?
+            .body;
+        if let Some(cache) = &self.tag_search_cache {
+            cache.insert(key, tags.clone());
+        }
+        Ok(tags)
+    }
+
+    fn get_siblings_and_parents(
+        &self,
+        tags: &[String],
+    ) -> Result<HashMap<String, HashMap<String, TagRelations>>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/get_siblings_and_parents");
+
+        let mut relations = HashMap::new();
+        for batch in tags.chunks(SIBLINGS_AND_PARENTS_BATCH_SIZE) {
+            relations.extend(
+                self.set_get_request_key(&req_url)?
+                    .query("tags", serde_json::to_string(batch)?)
+                    .call()?
+                    .body_mut()
+                    .read_json::<SiblingsAndParentsResponse>()?
+                    .tags,
+            );
+        }
+        Ok(relations)
+    }
+
+    fn tag_resolver(&self, tags: &[String], tag_service_key: &str) -> Result<TagResolver> {
+        let relations = self.get_siblings_and_parents(tags)?;
+        Ok(TagResolver::new(tag_service_key, &relations))
+    }
+
+    fn get_favourite_tags(&self) -> Result<Vec<String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/get_favourite_tags");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .call()?
+            .body_mut()
+            .read_json::<HydrusResponse<Vec<String>>>()?
+            .body)
+    }
+
+    fn set_favourite_tags(&self, change: FavouriteTagsChange) -> Result<Vec<String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/set_favourite_tags");
+
+        Ok(self
+            .set_post_request_key(&req_url)?
+            .send_json(&change)?
+            .body_mut()
+            .read_json::<HydrusResponse<Vec<String>>>()?
+            .body)
+    }
+
+    fn add_tags(
+        &self,
+        file: HydrusFile,
+        actions: HashMap<String, Vec<TagAction>>,
+        override_previously_deleted_mappings: Option<bool>,
+        create_new_deleted_mappings: Option<bool>,
+    ) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_tags/add_tags");
+
+        let form = AddTagsRequest::new(
+            file,
+            &actions,
+            override_previously_deleted_mappings,
+            create_new_deleted_mappings,
+        );
+
+        let _ = self.set_post_request_key(&req_url)?.send_json(&form)?;
+
+        // tag counts changed, so cached searches are stale
+        if let Some(cache) = &self.tag_search_cache {
+            cache.clear();
+        }
+        Ok(())
+    }
+}
+
+impl EditingFileNotes for HydrusClient {
+    fn set_notes(
+        &self,
+        file: SingleFile,
+        notes: HashMap<String, String>,
+        merge: NoteMerge,
+    ) -> Result<HashMap<String, String>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_notes/set_notes");
+
+        let form = SetNotesRequest::new(file, notes, merge);
+
+        Ok(self
+            .set_post_request_key(&req_url)?
+            .send_json(&form)?
+            .body_mut()
+            .read_json::<HydrusResponse<HashMap<String, String>>>()?
+            .body)
+    }
+
+    fn delete_notes(&self, file: SingleFile, note_names: Vec<String>) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("add_notes/delete_notes");
+
+        let form = DeleteNotesRequest { file, note_names };
+
+        let _ = self.set_post_request_key(&req_url)?.send_json(&form)?;
+
+        Ok(())
+    }
+}
+
+impl EditingFileRatings for HydrusClient {
+    fn set_rating(&self, file: SingleFile, service: &Service, rating: RatingValue) -> Result<()> {
+        rating.validate(service)?;
+
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("edit_ratings/set_rating");
+
+        let form = SetRatingRequest {
+            file,
+            rating_service_key: service.service_key.to_owned(),
+            rating,
+        };
+
+        let _ = self.set_post_request_key(&req_url)?.send_json(&form)?;
+
+        Ok(())
+    }
+}
+
+impl EditingFileTimes for HydrusClient {
+    fn set_time(
+        &self,
+        file: SingleFile,
+        timestamp_type: TimestampType,
+        timestamp_ms: Option<u64>,
+    ) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("edit_times/set_time");
+
+        let form = SetTimeRequest::new(file, timestamp_type, timestamp_ms);
+
+        let _ = self.set_post_request_key(&req_url)?.send_json(&form)?;
+
+        Ok(())
+    }
+
+    fn increment_file_viewtime(
+        &self,
+        file: SingleFile,
+        canvas_type: CanvasType,
+        viewtime: f64,
+        views: Option<u64>,
+        timestamp_ms: Option<u64>,
+    ) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("edit_times/increment_file_viewtime");
+
+        let form = IncrementViewtimeRequest {
+            file,
+            canvas_type,
+            viewtime,
+            views,
+            timestamp_ms,
+        };
+
+        let _ = self.set_post_request_key(&req_url)?.send_json(&form)?;
+
+        Ok(())
+    }
+}
+
+impl ManagingFileRelationships for HydrusClient {
+    fn get_file_relationships(
+        &self,
+        file: HydrusFile,
+        domain: Option<FileDomain>,
+    ) -> Result<HashMap<String, FileRelationships>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/get_file_relationships");
+
+        let (name, value) = file.query_param();
+        let mut request = self.set_get_request_key(&req_url)?.query(name, value);
+        if let Some(domain) = domain {
+            let (name, value) = domain.query_param();
+            request = request.query(name, value);
+        }
+
+        Ok(request
+            .call()?
+            .body_mut()
+            .read_json::<HydrusResponse<HashMap<String, FileRelationships>>>()?
+            .body)
+    }
+
+    fn get_potentials_count(&self, search: &PotentialsSearch) -> Result<u64> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/get_potentials_count");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query_pairs(search.query_params()?)
+            .call()?
+            .body_mut()
+            .read_json::<HydrusResponse<u64>>()?
+            .body)
+    }
+
+    fn get_potential_pairs(
+        &self,
+        search: &PotentialsSearch,
+        max_num_pairs: Option<u64>,
+    ) -> Result<Vec<(Hash, Hash)>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/get_potential_pairs");
+
+        let mut request = self
+            .set_get_request_key(&req_url)?
+            .query_pairs(search.query_params()?);
+        if let Some(max_num_pairs) = max_num_pairs {
+            request = request.query("max_num_pairs", max_num_pairs.to_string());
+        }
+
+        Ok(request
+            .call()?
+            .body_mut()
+            .read_json::<HydrusResponse<Vec<(Hash, Hash)>>>()?
+            .body)
+    }
+
+    fn get_random_potentials(&self, search: &PotentialsSearch) -> Result<Vec<Hash>> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/get_random_potentials");
+
+        Ok(self
+            .set_get_request_key(&req_url)?
+            .query_pairs(search.query_params()?)
+            .call()?
+            .body_mut()
+            .read_json::<HydrusResponse<Vec<Hash>>>()?
+            .body)
+    }
+
+    fn set_file_relationships(&self, relationships: &[RelationshipDecision]) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/set_file_relationships");
+
+        let form = SetRelationshipsRequest { relationships };
+
+        let _ = self.set_post_request_key(&req_url)?.send_json(&form)?;
+
+        Ok(())
+    }
+
+    fn set_kings(&self, file: HydrusFile) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/set_kings");
+
+        let _ = self.set_post_request_key(&req_url)?.send_json(&file)?;
+
+        Ok(())
+    }
+
+    fn remove_potentials(&self, file: HydrusFile) -> Result<()> {
+        let mut req_url = self.url.to_owned();
+        req_url.push_str("manage_file_relationships/remove_potentials");
+
+        let _ = self.set_post_request_key(&req_url)?.send_json(&file)?;
+
+        Ok(())
+    }
+}
+
+/// file access which reads files straight from the client's disk when the key may see local
+/// paths, and downloads them otherwise
+pub struct LocalFileAccess<'a> {
+    client: &'a HydrusClient,
+    local: bool,
+}
+
+impl<'a> LocalFileAccess<'a> {
+    /// check the client's key for the `SeeLocalPaths` permission
+    pub fn new(client: &'a HydrusClient) -> Result<LocalFileAccess<'a>> {
+        let key = client
+            .sessionkey
+            .as_ref()
+            .or(client.apikey.as_ref())
+            .ok_or(HydrusError::KeyNotSupplied)?;
+        let info = client.verify_access_key(key)?;
+        let local = info.permits_everything
+            || info
+                .basic_permissions
+                .contains(&HydrusPermissions::SeeLocalPaths);
+
+        Ok(LocalFileAccess { client, local })
+    }
+
+    /// whether files are read from disk
+    pub fn is_local(&self) -> bool {
+        self.local
+    }
+
+    /// open a file. files the client's paths do not lead to, such as when it runs on another
+    /// machine, are downloaded
+    pub fn open(&self, file: SingleFile) -> Result<FileDownload<FileReader>> {
+        if self.local
+            && let Ok(location) = self.client.file_path(file.clone())
+            && let Ok(local_file) = File::open(&location.path)
+        {
+            return Ok(FileDownload {
+                info: DownloadInfo {
+                    mime: Some(location.filetype),
+                    size: Some(location.size),
+                    ..Default::default()
+                },
+                body: Box::new(local_file),
+            });
+        }
+
+        self.client.get_file(file, false)
+    }
+}
+
+impl HydrusClient {
+    /// walk potential duplicate pairs and resolve them by `rules`, a page of pairs at a time. in
+    /// dry-run mode the decisions for the first page are only reported
+    pub fn resolve_duplicates(&self, rules: &DuplicateRules) -> Result<ResolutionReport> {
+        let mut run = ResolutionRun::new(rules);
+        while !run.is_done() {
+            let page = self.get_potential_pairs(&rules.search, rules.max_pairs)?;
+            let pairs = run.new_pairs(page);
+            if pairs.is_empty() {
+                break;
+            }
+
+            let mut metadata = Vec::new();
+            for batch in ResolutionRun::metadata_batches(&pairs) {
+                metadata.extend(self.file_metadata(batch, FileMetadataOptions::default())?);
+            }
+
+            let decisions = run.plan(&pairs, metadata);
+            if !decisions.is_empty() {
+                self.set_file_relationships(&decisions)?;
+            }
+        }
+        Ok(run.finish())
+    }
 }
@@ -1,6 +1,9 @@
 use std::{collections::HashMap, path::PathBuf};
 
 use crate::sync_lib::HydrusError;
+use crate::types::duplicates::*;
+use crate::types::search::*;
+use crate::types::tags::*;
 use crate::types::*;
 
 type Result<T> = std::result::Result<T, HydrusError>;
@@ -67,4 +70,165 @@ pub trait ImportingAndEditingUrls {
         url: &str,
         doublecheck_file_system: Option<bool>,
     ) -> Result<FilesUrlResponse>;
+    /// Tell the client to import an url.
+    fn add_url(&self, url: &str, options: AddUrlOptions) -> Result<AddUrlResponse>;
+    /// Ask the client how it recognises an url.
+    fn get_url_info(&self, url: &str) -> Result<UrlInfo>;
+    /// Add urls to and delete urls from files. Urls are normalised unless `normalise_urls` is false.
+    fn associate_url(
+        &self,
+        file: HydrusFile,
+        urls_to_add: Vec<String>,
+        urls_to_delete: Vec<String>,
+        normalise_urls: Option<bool>,
+    ) -> Result<()>;
+}
+
+/// Trait for searching and fetching files.
+pub trait SearchingAndFetchingFiles {
+    /// Search for the client's files.
+    fn search_files(
+        &self,
+        tags: &[SearchTag],
+        domain: Option<FileDomain>,
+        tag_service_key: Option<String>,
+        sort_type: Option<FileSortType>,
+        sort_direction: Option<SortDirection>,
+        returns: SearchReturns,
+    ) -> Result<SearchFilesResponse>;
+    /// Get metadata about files in the client.
+    fn file_metadata(
+        &self,
+        file: HydrusFile,
+        options: FileMetadataOptions,
+    ) -> Result<Vec<FileMetadata>>;
+    /// Get a file as a reader. With `download` set the client suggests a file name.
+    fn get_file(&self, file: SingleFile, download: bool) -> Result<FileDownload<FileReader>>;
+    /// Render a file, such as a psd or an animation, to an image format browsers can show.
+    fn render_file(&self, file: SingleFile, options: RenderOptions) -> Result<RenderedFile>;
+    /// Download a file to a local path. If the path is a directory, the file name suggested by the client is used.
+    /// The file is written to `<name>.part` next to it and moved into place when done, resuming an earlier `.part` file.
+    /// Files requested by hash are checked before the move. An existing file at the path is an error and is left alone.
+    fn download_to_path(
+        &self,
+        file: SingleFile,
+        path: PathBuf,
+        progress: Option<&(dyn Fn(DownloadProgress) + Send + Sync)>,
+    ) -> Result<PathBuf>;
+    /// Get a file's thumbnail. With `blurhash_fallback` set, the file's blurhash is returned if the thumbnail cannot be fetched.
+    fn get_thumbnail(&self, file: SingleFile, blurhash_fallback: bool) -> Result<Thumbnail>;
+    /// Get the path of a file on the client's disk.
+    fn file_path(&self, file: SingleFile) -> Result<LocalFilePath>;
+    /// Get the path of a file's thumbnail on the client's disk.
+    fn thumbnail_path(&self, file: SingleFile) -> Result<LocalThumbnailPath>;
+    /// Get the directories the client stores files and thumbnails in.
+    fn local_file_storage_locations(&self) -> Result<Vec<StorageLocation>>;
+    /// Convert hashes to another hash type, mapping each known source hash to its converted hash.
+    /// Large lists are split over several requests.
+    fn file_hashes(
+        &self,
+        hashes: &[String],
+        source: HashType,
+        desired: HashType,
+    ) -> Result<HashMap<String, String>>;
+}
+
+/// Trait for editing file tags.
+pub trait EditingFileTags {
+    /// Clean tags the way the client would store them. Tags left empty are dropped.
+    fn clean_tags(&self, tags: &[String]) -> Result<Vec<String>>;
+    /// Search tags for autocomplete. With a tag search cache set, recent searches are answered from memory.
+    fn search_tags(
+        &self,
+        search: &str,
+        tag_service_key: Option<String>,
+        domain: Option<FileDomain>,
+        display_type: TagDisplayType,
+    ) -> Result<Vec<TagCount>>;
+    /// Get the siblings and parents of tags, mapping each tag to its relations by tag service key.
+    /// Large lists are split over several requests.
+    fn get_siblings_and_parents(
+        &self,
+        tags: &[String],
+    ) -> Result<HashMap<String, HashMap<String, TagRelations>>>;
+    /// Load the relations of tags on a tag service for resolving them offline.
+    fn tag_resolver(&self, tags: &[String], tag_service_key: &str) -> Result<TagResolver>;
+    /// Get the user's favourite tags.
+    fn get_favourite_tags(&self) -> Result<Vec<String>>;
+    /// Change the user's favourite tags, returning the new favourite tags.
+    fn set_favourite_tags(&self, change: FavouriteTagsChange) -> Result<Vec<String>>;
+    /// Add or remove tags, mapping tag service keys to the actions to apply on them.
+    fn add_tags(
+        &self,
+        file: HydrusFile,
+        actions: HashMap<String, Vec<TagAction>>,
+        override_previously_deleted_mappings: Option<bool>,
+        create_new_deleted_mappings: Option<bool>,
+    ) -> Result<()>;
+}
+
+/// Trait for editing file notes.
+pub trait EditingFileNotes {
+    /// Set notes on a file identified by id or hash, returning the notes the client stored after merging.
+    fn set_notes(
+        &self,
+        file: SingleFile,
+        notes: HashMap<String, String>,
+        merge: NoteMerge,
+    ) -> Result<HashMap<String, String>>;
+    /// Delete notes by name from a file identified by id or hash.
+    fn delete_notes(&self, file: SingleFile, note_names: Vec<String>) -> Result<()>;
+}
+
+/// Trait for editing file ratings.
+pub trait EditingFileRatings {
+    /// Set a file's rating on a rating service. Ratings the service cannot hold fail without a request.
+    fn set_rating(&self, file: SingleFile, service: &Service, rating: RatingValue) -> Result<()>;
+}
+
+/// Trait for editing file times.
+pub trait EditingFileTimes {
+    /// Set a file time in milliseconds since the epoch. `None` removes the time, where the client allows it.
+    fn set_time(
+        &self,
+        file: SingleFile,
+        timestamp_type: TimestampType,
+        timestamp_ms: Option<u64>,
+    ) -> Result<()>;
+    /// Add views and seconds of viewtime to a file's viewing statistics.
+    /// `timestamp_ms` sets the last viewed time, which defaults to now.
+    fn increment_file_viewtime(
+        &self,
+        file: SingleFile,
+        canvas_type: CanvasType,
+        viewtime: f64,
+        views: Option<u64>,
+        timestamp_ms: Option<u64>,
+    ) -> Result<()>;
+}
+
+/// Trait for managing file relationships.
+pub trait ManagingFileRelationships {
+    /// Get the relationships of files, by hash.
+    fn get_file_relationships(
+        &self,
+        file: HydrusFile,
+        domain: Option<FileDomain>,
+    ) -> Result<HashMap<String, FileRelationships>>;
+    /// Count the potential duplicate pairs matching a search.
+    fn get_potentials_count(&self, search: &PotentialsSearch) -> Result<u64>;
+    /// Get potential duplicate pairs matching a search, as the client's duplicate filter shows them.
+    fn get_potential_pairs(
+        &self,
+        search: &PotentialsSearch,
+        max_num_pairs: Option<u64>,
+    ) -> Result<Vec<(Hash, Hash)>>;
+    /// Get a random group of potential duplicates matching a search.
+    fn get_random_potentials(&self, search: &PotentialsSearch) -> Result<Vec<Hash>>;
+    /// Set relationships between pairs of files.
+    fn set_file_relationships(&self, relationships: &[RelationshipDecision]) -> Result<()>;
+    /// Make files the kings of their duplicate groups.
+    fn set_kings(&self, file: HydrusFile) -> Result<()>;
+    /// Remove all potential duplicate pairs files are part of.
+    fn remove_potentials(&self, file: HydrusFile) -> Result<()>;
 }
@@ -1,7 +1,28 @@
-use std::path::PathBuf;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+};
 
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "async")]
+use bytes::Bytes;
+#[cfg(feature = "async")]
+use futures_util::Stream;
+use serde::{Deserialize, Serialize, Serializer};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use thiserror::Error;
+
+/// types for managing file relationships and duplicates
+pub mod duplicates;
+/// types for searching files
+pub mod search;
+/// types for editing tags
+pub mod tags;
+
+/// sha256 hash of a file, in hex
+pub type Hash = String;
 
 /// Hydrus service permissions object
 #[derive(PartialEq, Debug, Clone, Serialize_repr, Deserialize_repr)]
@@ -75,7 +96,32 @@ pub struct Service {
     pub max_stars: Option<u8>,
 }
 
+/// hash algorithms known to hydrus
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum HashType {
+    Sha256,
+    Md5,
+    Sha1,
+    Sha512,
+}
+
+impl HashType {
+    /// name of the hash type as used by hydrus
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashType::Sha256 => "sha256",
+            HashType::Md5 => "md5",
+            HashType::Sha1 => "sha1",
+            HashType::Sha512 => "sha512",
+        }
+    }
+}
+
+/// hashes converted per `get_files/file_hashes` request
+pub(crate) const FILE_HASHES_BATCH_SIZE: usize = 100;
+
 /// Hydrus file domains
+#[derive(Debug, Clone)]
 pub enum FileDomain {
     FileServiceKey(String),
     FileServiceKeys(Vec<String>),
@@ -83,6 +129,23 @@ pub enum FileDomain {
     DeletedFileServiceKeys(Vec<String>),
 }
 
+impl FileDomain {
+    /// query parameter name and value for GET requests
+    pub(crate) fn query_param(&self) -> (&'static str, String) {
+        match self {
+            FileDomain::FileServiceKey(key) => ("file_service_key", key.to_owned()),
+            FileDomain::FileServiceKeys(keys) => {
+                ("file_service_keys", serde_json::json!(keys).to_string())
+            }
+            FileDomain::DeletedFileServiceKey(key) => ("deleted_file_service_key", key.to_owned()),
+            FileDomain::DeletedFileServiceKeys(keys) => (
+                "deleted_file_service_keys",
+                serde_json::json!(keys).to_string(),
+            ),
+        }
+    }
+}
+
 /// Payload for importing a file via providing a local path
 #[derive(Serialize, Debug, Default)]
 pub(crate) struct AddFileRequest {
@@ -129,12 +192,52 @@ pub enum HydrusFile {
     Hashes(Vec<String>),
 }
 
+impl HydrusFile {
+    /// query parameter name and value for GET requests
+    pub(crate) fn query_param(&self) -> (&'static str, String) {
+        match self {
+            HydrusFile::FileId(id) => ("file_id", id.to_owned()),
+            HydrusFile::FileIds(ids) => ("file_ids", format!("[{}]", ids.join(","))),
+            HydrusFile::Hash(hash) => ("hash", hash.to_owned()),
+            HydrusFile::Hashes(hashes) => ("hashes", serde_json::json!(hashes).to_string()),
+        }
+    }
+}
+
 impl Default for HydrusFile {
     fn default() -> Self {
         Self::FileId(String::from(""))
     }
 }
 
+/// a single file, for endpoints which do not take several files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum SingleFile {
+    #[serde(rename(serialize = "file_id"))]
+    FileId(u64),
+    #[serde(rename(serialize = "hash"))]
+    Hash(String),
+}
+
+impl SingleFile {
+    /// query parameter name and value for GET requests
+    pub(crate) fn query_param(&self) -> (&'static str, String) {
+        match self {
+            SingleFile::FileId(id) => ("file_id", id.to_string()),
+            SingleFile::Hash(hash) => ("hash", hash.to_owned()),
+        }
+    }
+}
+
+impl From<SingleFile> for HydrusFile {
+    fn from(file: SingleFile) -> Self {
+        match file {
+            SingleFile::FileId(id) => HydrusFile::FileId(id.to_string()),
+            SingleFile::Hash(hash) => HydrusFile::Hash(hash),
+        }
+    }
+}
+
 /// Payload for various file-related requests
 #[derive(Debug, Default, Serialize)]
 pub(crate) struct FileRequest {
@@ -153,6 +256,79 @@ pub(crate) struct FileRequest {
     pub reason: Option<String>,
 }
 
+/// what to do with a note when the file already has a different note of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr)]
+#[repr(u8)]
+pub enum NoteConflict {
+    Replace = 0,
+    Ignore,
+    Append,
+    /// keep both notes, renaming the new one
+    Rename,
+}
+
+/// how new notes are merged with a file's existing notes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoteMerge {
+    /// overwrite existing notes of the same name
+    #[default]
+    Replace,
+    /// skip notes already contained in existing notes and resolve conflicts as given
+    Clever {
+        /// replace existing notes which the new note extends, rather than treating it as a conflict
+        extend_existing_note_if_possible: bool,
+        conflict_resolution: NoteConflict,
+    },
+}
+
+/// Payload for setting notes
+#[derive(Debug, Serialize)]
+pub(crate) struct SetNotesRequest {
+    #[serde(flatten)]
+    pub file: SingleFile,
+    pub notes: HashMap<String, String>,
+    pub merge_cleverly: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extend_existing_note_if_possible: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict_resolution: Option<NoteConflict>,
+}
+
+impl SetNotesRequest {
+    pub(crate) fn new(
+        file: SingleFile,
+        notes: HashMap<String, String>,
+        merge: NoteMerge,
+    ) -> SetNotesRequest {
+        let (merge_cleverly, extend_existing_note_if_possible, conflict_resolution) = match merge {
+            NoteMerge::Replace => (false, None, None),
+            NoteMerge::Clever {
+                extend_existing_note_if_possible,
+                conflict_resolution,
+            } => (
+                true,
+                Some(extend_existing_note_if_possible),
+                Some(conflict_resolution),
+            ),
+        };
+        SetNotesRequest {
+            file,
+            notes,
+            merge_cleverly,
+            extend_existing_note_if_possible,
+            conflict_resolution,
+        }
+    }
+}
+
+/// Payload for deleting notes
+#[derive(Debug, Serialize)]
+pub(crate) struct DeleteNotesRequest {
+    #[serde(flatten)]
+    pub file: SingleFile,
+    pub note_names: Vec<String>,
+}
+
 /// object for per-item hash response
 #[derive(Debug, Deserialize)]
 pub struct HashResponse {
@@ -164,12 +340,33 @@ pub struct HashResponse {
 }
 
 /// url status in hydrus database
-#[derive(Debug, Deserialize_repr)]
-#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(from = "u8")]
 pub enum UrlStatus {
-    NotInDatabase = 0,
-    AlreadyInDatabase = 2,
+    NotInDatabase,
+    SuccessfulImport,
+    AlreadyInDatabase,
     PreviouslyDeleted,
+    Error,
+    Vetoed,
+    Skipped,
+    /// status unknown to this crate
+    Other(u8),
+}
+
+impl From<u8> for UrlStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => UrlStatus::NotInDatabase,
+            1 => UrlStatus::SuccessfulImport,
+            2 => UrlStatus::AlreadyInDatabase,
+            3 => UrlStatus::PreviouslyDeleted,
+            4 => UrlStatus::Error,
+            7 => UrlStatus::Vetoed,
+            8 => UrlStatus::Skipped,
+            other => UrlStatus::Other(other),
+        }
+    }
 }
 
 /// per-url response object
@@ -186,3 +383,850 @@ pub struct FilesUrlResponse {
     pub normalised_url: String,
     pub url_file_statuses: Vec<UrlFileStatus>,
 }
+
+/// how the client recognises an url
+#[derive(Debug, Clone, Deserialize)]
+pub struct UrlInfo {
+    pub normalised_url: String,
+    pub url_type: UrlType,
+    pub url_type_string: String,
+    /// name of the matching url class
+    pub match_name: String,
+    /// whether the client has a parser for the url
+    pub can_parse: bool,
+    #[serde(default)]
+    pub cannot_parse_reason: Option<String>,
+}
+
+/// page an url is imported into
+#[derive(Debug, Clone)]
+pub enum DestinationPage {
+    Key(String),
+    Name(String),
+}
+
+/// options for importing an url
+#[derive(Debug, Clone, Default)]
+pub struct AddUrlOptions {
+    /// page to import into, the client picks or creates one if not given
+    pub destination_page: Option<DestinationPage>,
+    /// switch to the destination page
+    pub show_destination_page: Option<bool>,
+    /// tags to add to imported files, by tag service key
+    pub service_keys_to_additional_tags: HashMap<String, Vec<String>>,
+    /// tags parsed by the downloader which the page's tag filter may also apply
+    pub filterable_tags: Vec<String>,
+    /// key of the local file domain to import files to
+    pub destination_file_service_key: Option<String>,
+}
+
+/// Payload for importing an url
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct AddUrlRequest {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination_page_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination_page_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_destination_page: Option<bool>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub service_keys_to_additional_tags: HashMap<String, Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filterable_tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_service_key: Option<String>,
+}
+
+impl AddUrlRequest {
+    pub(crate) fn new(url: &str, options: AddUrlOptions) -> AddUrlRequest {
+        let mut form = AddUrlRequest {
+            url: url.to_string(),
+            show_destination_page: options.show_destination_page,
+            service_keys_to_additional_tags: options.service_keys_to_additional_tags,
+            filterable_tags: options.filterable_tags,
+            file_service_key: options.destination_file_service_key,
+            ..Default::default()
+        };
+
+        match options.destination_page {
+            Some(DestinationPage::Key(key)) => form.destination_page_key = Some(key),
+            Some(DestinationPage::Name(name)) => form.destination_page_name = Some(name),
+            None => {}
+        }
+
+        form
+    }
+}
+
+/// hydrus response after importing an url
+#[derive(Debug, Clone, Deserialize)]
+pub struct AddUrlResponse {
+    pub human_result_text: String,
+    pub normalised_url: String,
+}
+
+/// Payload for adding urls to and deleting urls from files
+#[derive(Serialize, Debug)]
+pub(crate) struct AssociateUrlRequest {
+    #[serde(flatten)]
+    pub file: HydrusFile,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub urls_to_add: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub urls_to_delete: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalise_urls: Option<bool>,
+}
+
+/// flags for querying file metadata
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileMetadataOptions {
+    /// only return file ids and hashes
+    pub only_return_identifiers: bool,
+    /// only return basic information such as size, mime and resolution
+    pub only_return_basic_information: bool,
+    /// include file notes
+    pub include_notes: bool,
+    /// include the blurhash of the thumbnail
+    pub include_blurhash: bool,
+    /// include parsing information for known urls
+    pub detailed_url_information: bool,
+    /// return times with millisecond precision
+    pub include_milliseconds: bool,
+}
+
+/// tags of a file, by tag status
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TagsByStatus {
+    #[serde(rename = "0", default)]
+    pub current: Vec<String>,
+    #[serde(rename = "1", default)]
+    pub pending: Vec<String>,
+    #[serde(rename = "2", default)]
+    pub deleted: Vec<String>,
+    #[serde(rename = "3", default)]
+    pub petitioned: Vec<String>,
+}
+
+/// tags of a file on a single tag service
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceTags {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub r#type: Option<ServiceType>,
+    #[serde(default)]
+    pub type_pretty: String,
+    /// tags as stored, without siblings and parents applied
+    #[serde(default)]
+    pub storage_tags: TagsByStatus,
+    /// tags as shown in the client, with siblings and parents applied
+    #[serde(default)]
+    pub display_tags: TagsByStatus,
+}
+
+/// file service membership of a file
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileServiceTimes {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub r#type: Option<ServiceType>,
+    #[serde(default)]
+    pub type_pretty: String,
+    /// seconds since the epoch, with milliseconds if requested
+    #[serde(default)]
+    pub time_imported: Option<f64>,
+    #[serde(default)]
+    pub time_deleted: Option<f64>,
+}
+
+/// file services a file is currently in or was deleted from
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FileServices {
+    #[serde(default)]
+    pub current: HashMap<String, FileServiceTimes>,
+    #[serde(default)]
+    pub deleted: HashMap<String, FileServiceTimes>,
+}
+
+/// kind of url, as recognised by the client's url classes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(from = "u8")]
+pub enum UrlType {
+    Post,
+    File,
+    Gallery,
+    Watchable,
+    Unknown,
+    /// type unknown to this crate
+    Other(u8),
+}
+
+impl From<u8> for UrlType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => UrlType::Post,
+            2 => UrlType::File,
+            3 => UrlType::Gallery,
+            4 => UrlType::Watchable,
+            5 => UrlType::Unknown,
+            other => UrlType::Other(other),
+        }
+    }
+}
+
+/// parsing information for a known url
+#[derive(Debug, Clone, Deserialize)]
+pub struct DetailedUrl {
+    pub normalised_url: String,
+    pub url_type: UrlType,
+    pub url_type_string: String,
+    pub match_name: String,
+    pub can_parse: bool,
+}
+
+/// rating of a file. like/dislike services give a boolean, numerical and inc/dec services a number
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum FileRating {
+    Boolean(bool),
+    Numerical(u64),
+}
+
+/// rating to set on a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatingValue {
+    /// like/dislike services only
+    Like,
+    /// like/dislike services only
+    Dislike,
+    /// numerical services only
+    Stars(u8),
+    /// inc/dec services only
+    Counter(u64),
+    /// remove the rating, like/dislike and numerical services only
+    Clear,
+}
+
+impl RatingValue {
+    /// check the rating can be set on `service`
+    pub fn validate(&self, service: &Service) -> Result<(), RatingError> {
+        let fits = match (self, &service.r#type) {
+            (RatingValue::Like | RatingValue::Dislike, ServiceType::BoolRating) => true,
+            (RatingValue::Stars(stars), ServiceType::NumericalRating) => {
+                let min = service.min_stars.unwrap_or(0);
+                let max = service.max_stars.unwrap_or(u8::MAX);
+                if !(min..=max).contains(stars) {
+                    return Err(RatingError::StarsOutOfRange {
+                        stars: *stars,
+                        min,
+                        max,
+                    });
+                }
+                true
+            }
+            (RatingValue::Counter(_), ServiceType::IncDecRating) => true,
+            (RatingValue::Clear, ServiceType::BoolRating | ServiceType::NumericalRating) => true,
+            (
+                _,
+                ServiceType::BoolRating | ServiceType::NumericalRating | ServiceType::IncDecRating,
+            ) => false,
+            _ => {
+                return Err(RatingError::NotARatingService(service.name.to_owned()));
+            }
+        };
+        if fits {
+            Ok(())
+        } else {
+            Err(RatingError::WrongRatingType {
+                rating: *self,
+                service: service.name.to_owned(),
+            })
+        }
+    }
+}
+
+impl Serialize for RatingValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            RatingValue::Like => serializer.serialize_bool(true),
+            RatingValue::Dislike => serializer.serialize_bool(false),
+            RatingValue::Stars(stars) => serializer.serialize_u8(*stars),
+            RatingValue::Counter(count) => serializer.serialize_u64(*count),
+            RatingValue::Clear => serializer.serialize_none(),
+        }
+    }
+}
+
+/// rating which does not fit the rating service
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RatingError {
+    #[error("{0} is not a rating service")]
+    NotARatingService(String),
+    #[error("{rating:?} cannot be set on {service}")]
+    WrongRatingType {
+        rating: RatingValue,
+        service: String,
+    },
+    #[error("{stars} stars is outside the service's {min} to {max} stars")]
+    StarsOutOfRange { stars: u8, min: u8, max: u8 },
+}
+
+/// Payload for setting a rating
+#[derive(Debug, Serialize)]
+pub(crate) struct SetRatingRequest {
+    #[serde(flatten)]
+    pub file: SingleFile,
+    pub rating_service_key: String,
+    pub rating: RatingValue,
+}
+
+/// viewer a file was viewed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum CanvasType {
+    MediaViewer,
+    PreviewViewer,
+    ClientApi,
+    /// viewer unknown to this crate
+    Other(u8),
+}
+
+impl From<u8> for CanvasType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => CanvasType::MediaViewer,
+            1 => CanvasType::PreviewViewer,
+            4 => CanvasType::ClientApi,
+            other => CanvasType::Other(other),
+        }
+    }
+}
+
+impl From<CanvasType> for u8 {
+    fn from(value: CanvasType) -> Self {
+        match value {
+            CanvasType::MediaViewer => 0,
+            CanvasType::PreviewViewer => 1,
+            CanvasType::ClientApi => 4,
+            CanvasType::Other(other) => other,
+        }
+    }
+}
+
+/// file time to set, with the web domain, file service or viewer it is kept for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampType {
+    /// last modified time on a web domain
+    ModifiedOnDomain(String),
+    /// last modified time of the file on disk
+    ModifiedOnDisk,
+    /// import time on a file service
+    Imported(String),
+    /// deletion time on a file service
+    Deleted(String),
+    Archived,
+    LastViewed(CanvasType),
+    /// import time on a file service before the file was deleted from it
+    OriginallyImported(String),
+}
+
+/// Payload for setting a file time
+#[derive(Debug, Serialize)]
+pub(crate) struct SetTimeRequest {
+    #[serde(flatten)]
+    pub file: SingleFile,
+    /// none removes the time, where hydrus allows it
+    pub timestamp_ms: Option<u64>,
+    pub timestamp_type: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_service_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canvas_type: Option<CanvasType>,
+}
+
+impl SetTimeRequest {
+    pub(crate) fn new(
+        file: SingleFile,
+        timestamp_type: TimestampType,
+        timestamp_ms: Option<u64>,
+    ) -> SetTimeRequest {
+        let mut form = SetTimeRequest {
+            file,
+            timestamp_ms,
+            timestamp_type: 0,
+            domain: None,
+            file_service_key: None,
+            canvas_type: None,
+        };
+        match timestamp_type {
+            TimestampType::ModifiedOnDomain(domain) => form.domain = Some(domain),
+            TimestampType::ModifiedOnDisk => form.timestamp_type = 1,
+            TimestampType::Imported(key) => {
+                form.timestamp_type = 3;
+                form.file_service_key = Some(key);
+            }
+            TimestampType::Deleted(key) => {
+                form.timestamp_type = 4;
+                form.file_service_key = Some(key);
+            }
+            TimestampType::Archived => form.timestamp_type = 5,
+            TimestampType::LastViewed(canvas_type) => {
+                form.timestamp_type = 6;
+                form.canvas_type = Some(canvas_type);
+            }
+            TimestampType::OriginallyImported(key) => {
+                form.timestamp_type = 7;
+                form.file_service_key = Some(key);
+            }
+        }
+        form
+    }
+}
+
+/// Payload for adding file views
+#[derive(Debug, Serialize)]
+pub(crate) struct IncrementViewtimeRequest {
+    #[serde(flatten)]
+    pub file: SingleFile,
+    pub canvas_type: CanvasType,
+    /// seconds viewed
+    pub viewtime: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub views: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp_ms: Option<u64>,
+}
+
+/// file viewing statistics for a single canvas type
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileViewingStatistics {
+    pub canvas_type: CanvasType,
+    pub canvas_type_pretty: String,
+    pub views: u64,
+    /// viewtime in seconds
+    pub viewtime: f64,
+    #[serde(default)]
+    pub last_viewed_timestamp: Option<f64>,
+}
+
+/// file metadata. fields not requested via `FileMetadataOptions` are left empty
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileMetadata {
+    /// `None` for hashes the client does not know
+    pub file_id: Option<u64>,
+    pub hash: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub mime: Option<String>,
+    #[serde(default)]
+    pub filetype_human: Option<String>,
+    #[serde(default)]
+    pub filetype_enum: Option<u32>,
+    #[serde(default)]
+    pub ext: Option<String>,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub thumbnail_width: Option<u32>,
+    #[serde(default)]
+    pub thumbnail_height: Option<u32>,
+    /// duration in milliseconds
+    #[serde(default)]
+    pub duration: Option<u64>,
+    #[serde(default)]
+    pub num_frames: Option<u64>,
+    #[serde(default)]
+    pub num_words: Option<u64>,
+    #[serde(default)]
+    pub has_audio: Option<bool>,
+    #[serde(default)]
+    pub blurhash: Option<String>,
+    #[serde(default)]
+    pub pixel_hash: Option<String>,
+    #[serde(default)]
+    pub known_urls: Vec<String>,
+    #[serde(default)]
+    pub detailed_known_urls: Vec<DetailedUrl>,
+    /// tags by tag service key
+    #[serde(default)]
+    pub tags: HashMap<String, ServiceTags>,
+    /// note texts by note name
+    #[serde(default)]
+    pub notes: HashMap<String, String>,
+    /// ratings by rating service key, `None` if the file is not rated
+    #[serde(default)]
+    pub ratings: HashMap<String, Option<FileRating>>,
+    /// seconds since the epoch, with milliseconds if requested
+    #[serde(default)]
+    pub time_modified: Option<f64>,
+    /// modified times by source, `local` or a web domain
+    #[serde(default)]
+    pub time_modified_details: HashMap<String, f64>,
+    #[serde(default)]
+    pub file_services: FileServices,
+    #[serde(default)]
+    pub file_viewing_statistics: Vec<FileViewingStatistics>,
+    #[serde(default)]
+    pub is_inbox: Option<bool>,
+    #[serde(default)]
+    pub is_local: Option<bool>,
+    #[serde(default)]
+    pub is_trashed: Option<bool>,
+    #[serde(default)]
+    pub is_deleted: Option<bool>,
+    #[serde(default)]
+    pub has_exif: Option<bool>,
+    #[serde(default)]
+    pub has_human_readable_embedded_metadata: Option<bool>,
+    #[serde(default)]
+    pub has_icc_profile: Option<bool>,
+    #[serde(default)]
+    pub has_transparency: Option<bool>,
+}
+
+/// information about a file download, taken from the response headers
+#[derive(Debug, Clone, Default)]
+pub struct DownloadInfo {
+    pub mime: Option<String>,
+    /// size of the whole file
+    pub size: Option<u64>,
+    /// position in the file the download starts at, non-zero for resumed downloads
+    pub offset: u64,
+    /// file name suggested by the client, only sent for downloads with `download` set
+    pub filename: Option<String>,
+}
+
+impl DownloadInfo {
+    pub(crate) fn from_headers(
+        content_type: Option<&str>,
+        content_length: Option<&str>,
+        content_range: Option<&str>,
+        content_disposition: Option<&str>,
+    ) -> DownloadInfo {
+        // content-range looks like `bytes 100-999/1000`
+        let range = content_range
+            .and_then(|range| range.strip_prefix("bytes "))
+            .and_then(|range| range.split_once('/'));
+        let offset = range
+            .and_then(|(range, _)| range.split_once('-'))
+            .and_then(|(start, _)| start.parse().ok())
+            .unwrap_or(0);
+        let size = match range {
+            Some((_, total)) => total.parse().ok(),
+            None => content_length.and_then(|length| length.parse().ok()),
+        };
+        DownloadInfo {
+            mime: content_type.map(String::from),
+            size,
+            offset,
+            filename: content_disposition.and_then(|disposition| {
+                disposition
+                    .split(';')
+                    .find_map(|part| part.trim().strip_prefix("filename="))
+                    .map(|name| name.trim_matches('"').to_string())
+            }),
+        }
+    }
+}
+
+/// body of a sync file download
+pub type FileReader = Box<dyn Read + Send>;
+
+/// body of an async file download
+#[cfg(feature = "async")]
+pub type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// a file being downloaded from the client. the body is a `FileReader` for the sync client
+/// and a `ByteStream` for the async one
+pub struct FileDownload<B> {
+    pub info: DownloadInfo,
+    pub body: B,
+}
+
+impl<B: Read> Read for FileDownload<B> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.body.read(buf)
+    }
+}
+
+/// progress of a file download
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownloadProgress {
+    /// bytes of the file on disk, including those of earlier attempts
+    pub done: u64,
+    pub total: Option<u64>,
+}
+
+/// file a download to `path` is written to until it is complete, `<name>.part`
+pub(crate) fn part_path(path: &Path) -> std::io::Result<PathBuf> {
+    let mut name = path
+        .file_name()
+        .ok_or(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "download path has no file name",
+        ))?
+        .to_owned();
+    name.push(".part");
+    Ok(path.with_file_name(name))
+}
+
+/// downloads never replace files they did not create
+pub(crate) fn already_exists(path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::AlreadyExists,
+        format!("{} already exists", path.display()),
+    )
+}
+
+/// location of a file in the client's file storage
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocalFilePath {
+    pub path: PathBuf,
+    pub filetype: String,
+    pub size: u64,
+}
+
+/// location of a thumbnail in the client's file storage
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocalThumbnailPath {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub filetype: Option<String>,
+}
+
+/// a directory the client stores files and thumbnails in
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageLocation {
+    pub path: PathBuf,
+    pub ideal_weight: u64,
+    pub max_num_bytes: Option<u64>,
+    /// file ("f") and thumbnail ("t") prefixes stored here, such as "f3a"
+    pub prefixes: Vec<String>,
+}
+
+/// image format hydrus renders files to
+#[derive(Serialize_repr, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RenderFormat {
+    Jpeg = 1,
+    Png = 2,
+    Apng = 23,
+    Webp = 33,
+    AnimatedWebp = 83,
+}
+
+impl RenderFormat {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            RenderFormat::Jpeg => "image/jpeg",
+            RenderFormat::Png => "image/png",
+            RenderFormat::Apng => "image/apng",
+            RenderFormat::Webp | RenderFormat::AnimatedWebp => "image/webp",
+        }
+    }
+}
+
+/// options for rendering a file, hydrus picks png or apng when no format is given
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RenderOptions {
+    pub format: Option<RenderFormat>,
+    /// compression level (0-9) for png, quality (0-100) for jpeg and webp
+    pub quality: Option<u8>,
+    /// width and height to scale the render to
+    pub size: Option<(u32, u32)>,
+}
+
+impl RenderOptions {
+    pub(crate) fn query_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(format) = self.format {
+            params.push(("render_format", (format as u8).to_string()));
+        }
+        if let Some(quality) = self.quality {
+            params.push(("render_quality", quality.to_string()));
+        }
+        if let Some((width, height)) = self.size {
+            params.push(("width", width.to_string()));
+            params.push(("height", height.to_string()));
+        }
+        params
+    }
+}
+
+/// a file rendered by hydrus
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedFile {
+    pub mime: String,
+    pub bytes: Vec<u8>,
+}
+
+/// a file thumbnail
+#[derive(Debug, Clone, PartialEq)]
+pub enum Thumbnail {
+    Image {
+        mime: Option<String>,
+        bytes: Vec<u8>,
+    },
+    /// blurhash of the thumbnail, used when the thumbnail itself could not be fetched
+    Blurhash(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_url_codes_deserialize() {
+        let response: FilesUrlResponse = serde_json::from_str(
+            r#"{"normalised_url": "https://a.b/1", "url_file_statuses": [
+                {"status": 2, "hash": "ab", "note": ""},
+                {"status": 42, "hash": "cd", "note": ""}
+            ]}"#,
+        )
+        .unwrap();
+        let statuses: Vec<_> = response
+            .url_file_statuses
+            .iter()
+            .map(|status| status.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            [UrlStatus::AlreadyInDatabase, UrlStatus::Other(42)]
+        );
+        assert_eq!(
+            serde_json::from_str::<UrlType>("9").unwrap(),
+            UrlType::Other(9)
+        );
+    }
+
+    #[test]
+    fn unknown_files_have_no_id() {
+        let files: Vec<FileMetadata> = serde_json::from_str(
+            r#"[{"file_id": null, "hash": "ab"}, {"file_id": 3, "hash": "cd", "size": 10}]"#,
+        )
+        .unwrap();
+        assert_eq!(files[0].file_id, None);
+        assert_eq!(files[1].file_id, Some(3));
+    }
+
+    #[test]
+    fn downloads_go_to_part_files() {
+        assert_eq!(
+            part_path(Path::new("dir/file.png")).unwrap(),
+            Path::new("dir/file.png.part")
+        );
+        assert!(part_path(Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn serializes_url_imports() {
+        let request = AddUrlRequest::new(
+            "https://a.b/1",
+            AddUrlOptions {
+                destination_page: Some(DestinationPage::Key("cd".to_string())),
+                show_destination_page: Some(false),
+                destination_file_service_key: Some("ef".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "url": "https://a.b/1",
+                "destination_page_key": "cd",
+                "show_destination_page": false,
+                "file_service_key": "ef",
+            })
+        );
+
+        let request = AddUrlRequest::new(
+            "https://a.b/1",
+            AddUrlOptions {
+                destination_page: Some(DestinationPage::Name("downloads".to_string())),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "url": "https://a.b/1",
+                "destination_page_name": "downloads",
+            })
+        );
+    }
+
+    #[test]
+    fn serializes_file_times() {
+        let request = SetTimeRequest::new(
+            SingleFile::Hash("ab".to_string()),
+            TimestampType::LastViewed(CanvasType::ClientApi),
+            Some(1_700_000_000_123),
+        );
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "hash": "ab",
+                "timestamp_ms": 1_700_000_000_123u64,
+                "timestamp_type": 6,
+                "canvas_type": 4,
+            })
+        );
+
+        let statistics: Vec<FileViewingStatistics> = serde_json::from_str(
+            r#"[{"canvas_type": 1, "canvas_type_pretty": "preview viewer", "views": 2,
+                "viewtime": 1.5}, {"canvas_type": 9, "canvas_type_pretty": "new viewer",
+                "views": 0, "viewtime": 0.0}]"#,
+        )
+        .unwrap();
+        assert_eq!(statistics[0].canvas_type, CanvasType::PreviewViewer);
+        assert_eq!(statistics[1].canvas_type, CanvasType::Other(9));
+
+        let files: Vec<FileMetadata> = serde_json::from_str(
+            r#"[{"hash": "ab", "time_modified": 1500000000,
+                "time_modified_details": {"local": 1500000000.123}}]"#,
+        )
+        .unwrap();
+        assert_eq!(files[0].time_modified, Some(1_500_000_000.0));
+        assert_eq!(files[0].time_modified_details["local"], 1_500_000_000.123);
+    }
+
+    #[test]
+    fn validates_ratings_against_service() {
+        let stars: Service = serde_json::from_str(
+            r#"{"name": "stars", "service_key": "ab", "type": 6, "type_pretty": "numerical rating",
+                "star_shape": "circle", "min_stars": 0, "max_stars": 5}"#,
+        )
+        .unwrap();
+        assert!(RatingValue::Stars(5).validate(&stars).is_ok());
+        assert!(RatingValue::Clear.validate(&stars).is_ok());
+        assert_eq!(
+            RatingValue::Stars(6).validate(&stars),
+            Err(RatingError::StarsOutOfRange {
+                stars: 6,
+                min: 0,
+                max: 5
+            })
+        );
+        assert!(matches!(
+            RatingValue::Like.validate(&stars),
+            Err(RatingError::WrongRatingType { .. })
+        ));
+
+        let tags = Service {
+            r#type: ServiceType::LocalTagDomain,
+            ..stars
+        };
+        assert!(matches!(
+            RatingValue::Counter(1).validate(&tags),
+            Err(RatingError::NotARatingService(_))
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::Serialize_repr;
+
+use crate::types::{FileDomain, Hash, search::SearchTag};
+
+/// relationships of a file to other files, by hash
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct FileRelationships {
+    /// whether the file is the best file of its duplicate group
+    pub is_king: bool,
+    pub king: Option<Hash>,
+    /// whether the king is in the file domain searched
+    pub king_is_on_file_domain: bool,
+    #[serde(default)]
+    pub king_is_local: bool,
+    #[serde(rename = "0", default)]
+    pub potential_duplicates: Vec<Hash>,
+    #[serde(rename = "1", default)]
+    pub false_positives: Vec<Hash>,
+    #[serde(rename = "3", default)]
+    pub alternates: Vec<Hash>,
+    /// other members of the file's duplicate group
+    #[serde(rename = "8", default)]
+    pub duplicates: Vec<Hash>,
+}
+
+/// which files of a potential pair must match the searches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize_repr)]
+#[repr(u8)]
+pub enum PotentialsSearchType {
+    /// at least one file matches the first search
+    #[default]
+    OneMatchesFirst = 0,
+    /// both files match the first search
+    BothMatchFirst,
+    /// one file matches the first search and the other the second
+    OneMatchesEach,
+}
+
+/// whether potential pairs must be pixel duplicates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize_repr)]
+#[repr(u8)]
+pub enum PixelDuplicates {
+    Required = 0,
+    #[default]
+    Allowed,
+    Excluded,
+}
+
+/// search for potential duplicate pairs. empty tag searches match all files
+#[derive(Debug, Clone)]
+pub struct PotentialsSearch {
+    pub domain: Option<FileDomain>,
+    pub tag_service_key_1: Option<String>,
+    pub tags_1: Vec<SearchTag>,
+    pub tag_service_key_2: Option<String>,
+    pub tags_2: Vec<SearchTag>,
+    pub search_type: PotentialsSearchType,
+    pub pixel_duplicates: PixelDuplicates,
+    /// how different the perceptual hashes of a pair may be
+    pub max_hamming_distance: u8,
+}
+
+impl Default for PotentialsSearch {
+    fn default() -> Self {
+        PotentialsSearch {
+            domain: None,
+            tag_service_key_1: None,
+            tags_1: Vec::new(),
+            tag_service_key_2: None,
+            tags_2: Vec::new(),
+            search_type: PotentialsSearchType::default(),
+            pixel_duplicates: PixelDuplicates::default(),
+            max_hamming_distance: 4,
+        }
+    }
+}
+
+impl PotentialsSearch {
+    /// query parameters for GET requests
+    pub(crate) fn query_params(&self) -> serde_json::Result<Vec<(&'static str, String)>> {
+        let mut params = Vec::new();
+        if let Some(domain) = &self.domain {
+            params.push(domain.query_param());
+        }
+        if let Some(key) = &self.tag_service_key_1 {
+            params.push(("tag_service_key_1", key.to_owned()));
+        }
+        if !self.tags_1.is_empty() {
+            params.push(("tags_1", serde_json::to_string(&self.tags_1)?));
+        }
+        if let Some(key) = &self.tag_service_key_2 {
+            params.push(("tag_service_key_2", key.to_owned()));
+        }
+        if !self.tags_2.is_empty() {
+            params.push(("tags_2", serde_json::to_string(&self.tags_2)?));
+        }
+        params.push((
+            "potentials_search_type",
+            (self.search_type as u8).to_string(),
+        ));
+        params.push((
+            "pixel_duplicates",
+            (self.pixel_duplicates as u8).to_string(),
+        ));
+        params.push((
+            "max_hamming_distance",
+            self.max_hamming_distance.to_string(),
+        ));
+        Ok(params)
+    }
+}
+
+/// relationship to set between two files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr)]
+#[repr(u8)]
+pub enum Relationship {
+    PotentialDuplicates = 0,
+    FalsePositives = 1,
+    SameQuality = 2,
+    Alternates = 3,
+    ABetter = 4,
+    BBetter = 7,
+}
+
+/// a relationship to set between file a and file b
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RelationshipDecision {
+    pub hash_a: Hash,
+    pub hash_b: Hash,
+    pub relationship: Relationship,
+    /// merge tags, ratings, notes and urls between the files as the client's duplicate settings say
+    pub do_default_content_merge: bool,
+    pub delete_a: bool,
+    pub delete_b: bool,
+}
+
+/// Payload for setting file relationships
+#[derive(Debug, Serialize)]
+pub(crate) struct SetRelationshipsRequest<'a> {
+    pub relationships: &'a [RelationshipDecision],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn potentials_search_params() {
+        let search = PotentialsSearch {
+            tags_1: vec![SearchTag::Tag("blue eyes".into())],
+            search_type: PotentialsSearchType::BothMatchFirst,
+            pixel_duplicates: PixelDuplicates::Required,
+            max_hamming_distance: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            search.query_params().unwrap(),
+            [
+                ("tags_1", r#"["blue eyes"]"#.to_string()),
+                ("potentials_search_type", "1".to_string()),
+                ("pixel_duplicates", "0".to_string()),
+                ("max_hamming_distance", "0".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn serializes_relationship_decisions() {
+        let decision = |relationship| RelationshipDecision {
+            hash_a: "ab".to_string(),
+            hash_b: "cd".to_string(),
+            relationship,
+            do_default_content_merge: true,
+            delete_a: false,
+            delete_b: true,
+        };
+        let relationships = [
+            Relationship::PotentialDuplicates,
+            Relationship::FalsePositives,
+            Relationship::SameQuality,
+            Relationship::Alternates,
+            Relationship::ABetter,
+            Relationship::BBetter,
+        ]
+        .map(decision);
+        let request = serde_json::to_value(SetRelationshipsRequest {
+            relationships: &relationships,
+        })
+        .unwrap();
+
+        let codes: Vec<_> = request["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|decision| decision["relationship"].as_u64().unwrap())
+            .collect();
+        assert_eq!(codes, [0, 1, 2, 3, 4, 7]);
+        assert_eq!(
+            request["relationships"][0],
+            serde_json::json!({
+                "hash_a": "ab",
+                "hash_b": "cd",
+                "relationship": 0,
+                "do_default_content_merge": true,
+                "delete_a": false,
+                "delete_b": true,
+            })
+        );
+    }
+
+    #[test]
+    fn deserializes_relationships() {
+        let relationships: FileRelationships = serde_json::from_str(
+            r#"{
+                "is_king": false,
+                "king": "b54d09218e0d6efc964b78b070620a1fa19c7e069672b4c6313cee2c9b0623f2",
+                "king_is_on_file_domain": true,
+                "king_is_local": true,
+                "0": [],
+                "1": [],
+                "3": ["5fab162576617b5c3fc8caabea53ce3ab1a3c8e0a16c16ae7b4e4a21eab168a7"],
+                "8": ["b54d09218e0d6efc964b78b070620a1fa19c7e069672b4c6313cee2c9b0623f2"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(relationships.alternates.len(), 1);
+        assert_eq!(
+            relationships.duplicates,
+            relationships.king.into_iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,783 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize, Serializer, ser::SerializeSeq};
+use serde_repr::Serialize_repr;
+
+use crate::types::HashType;
+
+mod parser;
+
+pub use parser::{ParseError, ParseErrorKind};
+
+/// a tag as used in searches, may contain `*` wildcards
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TagPattern {
+    pub namespace: Option<String>,
+    pub subtag: String,
+}
+
+impl TagPattern {
+    /// split a tag into namespace and subtag
+    pub fn new(tag: &str) -> TagPattern {
+        match tag.split_once(':') {
+            Some((namespace, subtag)) if !namespace.is_empty() => TagPattern {
+                namespace: Some(namespace.to_string()),
+                subtag: subtag.to_string(),
+            },
+            _ => TagPattern {
+                namespace: None,
+                subtag: tag.to_string(),
+            },
+        }
+    }
+    /// whether the pattern matches several tags, e.g. `character:*` or `sam*`
+    pub fn is_wildcard(&self) -> bool {
+        self.subtag.contains('*')
+            || self
+                .namespace
+                .as_ref()
+                .is_some_and(|namespace| namespace.contains('*'))
+    }
+}
+
+impl From<&str> for TagPattern {
+    fn from(value: &str) -> Self {
+        TagPattern::new(value)
+    }
+}
+
+impl From<String> for TagPattern {
+    fn from(value: String) -> Self {
+        TagPattern::new(&value)
+    }
+}
+
+impl fmt::Display for TagPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(namespace) = &self.namespace {
+            write!(f, "{namespace}:")?;
+        }
+        f.write_str(&self.subtag)
+    }
+}
+
+/// a single entry of a file search
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchTag {
+    /// a tag files must have, e.g. `character:samus aran`
+    Tag(TagPattern),
+    /// a tag files must not have
+    Negated(TagPattern),
+    /// files must match at least one of the contained entries
+    Or(Vec<SearchTag>),
+    /// a system predicate, e.g. `system:width > 1000`
+    System(SystemPredicate),
+}
+
+impl Serialize for SearchTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            SearchTag::Tag(tag) => serializer.collect_str(tag),
+            SearchTag::Negated(tag) => serializer.collect_str(&format_args!("-{tag}")),
+            SearchTag::Or(tags) => {
+                let mut seq = serializer.serialize_seq(Some(tags.len()))?;
+                for tag in tags {
+                    seq.serialize_element(tag)?;
+                }
+                seq.end()
+            }
+            SearchTag::System(predicate) => serializer.collect_str(predicate),
+        }
+    }
+}
+
+/// builder for the tag list of a file search
+#[derive(Debug, Clone, Default)]
+pub struct SearchBuilder {
+    tags: Vec<SearchTag>,
+}
+
+impl SearchBuilder {
+    /// create an empty search
+    pub fn new() -> SearchBuilder {
+        SearchBuilder::default()
+    }
+    /// require files to have a tag
+    pub fn tag(mut self, tag: &str) -> SearchBuilder {
+        self.tags.push(SearchTag::Tag(TagPattern::new(tag)));
+        self
+    }
+    /// require files to not have a tag
+    pub fn exclude(mut self, tag: &str) -> SearchBuilder {
+        self.tags.push(SearchTag::Negated(TagPattern::new(tag)));
+        self
+    }
+    /// require files to match at least one of the entries
+    pub fn any_of(mut self, tags: Vec<SearchTag>) -> SearchBuilder {
+        self.tags.push(SearchTag::Or(tags));
+        self
+    }
+    /// require files to match a system predicate
+    pub fn system(mut self, predicate: SystemPredicate) -> SearchBuilder {
+        self.tags.push(SearchTag::System(predicate));
+        self
+    }
+    /// finish the search
+    pub fn build(self) -> Vec<SearchTag> {
+        self.tags
+    }
+}
+
+/// a complete file search. it can be parsed from and printed as search text such as
+/// `-character:foo system:width > 1000 (blue eyes OR green eyes)`.
+///
+/// terms are separated by whitespace, a leading `-` negates a tag and parenthesised
+/// alternatives are separated by `OR`. tags containing whitespace are written in double
+/// quotes outside of groups, with `\"` and `\\` as escapes. a quoted term starting with
+/// `system:` is a system predicate, so the `system` namespace cannot be used for tags.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchQuery {
+    pub tags: Vec<SearchTag>,
+}
+
+impl From<Vec<SearchTag>> for SearchQuery {
+    fn from(tags: Vec<SearchTag>) -> Self {
+        SearchQuery { tags }
+    }
+}
+
+/// comparison operator for system predicates
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Operator {
+    Less,
+    Equal,
+    NotEqual,
+    Approximately,
+    Greater,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operator::Less => "<",
+            Operator::Equal => "=",
+            Operator::NotEqual => "\u{2260}",
+            Operator::Approximately => "~=",
+            Operator::Greater => ">",
+        })
+    }
+}
+
+/// filesize units for `system:filesize`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SizeUnit {
+    Bytes,
+    Kilobytes,
+    Megabytes,
+    Gigabytes,
+}
+
+impl fmt::Display for SizeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SizeUnit::Bytes => "B",
+            SizeUnit::Kilobytes => "KB",
+            SizeUnit::Megabytes => "MB",
+            SizeUnit::Gigabytes => "GB",
+        })
+    }
+}
+
+/// pixel count units for `system:num pixels`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum PixelUnit {
+    Pixels,
+    Kilopixels,
+    Megapixels,
+}
+
+impl fmt::Display for PixelUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PixelUnit::Pixels => "pixels",
+            PixelUnit::Kilopixels => "kilopixels",
+            PixelUnit::Megapixels => "megapixels",
+        })
+    }
+}
+
+/// duration units for `system:duration`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum DurationUnit {
+    Milliseconds,
+    Seconds,
+}
+
+impl fmt::Display for DurationUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DurationUnit::Milliseconds => "milliseconds",
+            DurationUnit::Seconds => "seconds",
+        })
+    }
+}
+
+/// ratio comparison for `system:ratio`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum RatioOperator {
+    Equal,
+    WiderThan,
+    TallerThan,
+}
+
+impl fmt::Display for RatioOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RatioOperator::Equal => "=",
+            RatioOperator::WiderThan => "wider than",
+            RatioOperator::TallerThan => "taller than",
+        })
+    }
+}
+
+/// point in time for time based system predicates
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TimeValue {
+    /// time relative to now. `<` matches files newer than this, `>` files older than this
+    Ago {
+        years: u32,
+        months: u32,
+        days: u32,
+        hours: u32,
+    },
+    /// calendar date
+    Date { year: u16, month: u8, day: u8 },
+}
+
+impl fmt::Display for TimeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeValue::Ago {
+                years,
+                months,
+                days,
+                hours,
+            } => {
+                let parts: Vec<String> = [
+                    (*years, "year"),
+                    (*months, "month"),
+                    (*days, "day"),
+                    (*hours, "hour"),
+                ]
+                .into_iter()
+                .filter(|(value, _)| *value != 0)
+                .map(|(value, unit)| {
+                    if value == 1 {
+                        format!("{value} {unit}")
+                    } else {
+                        format!("{value} {unit}s")
+                    }
+                })
+                .collect();
+                if parts.is_empty() {
+                    f.write_str("0 days")
+                } else {
+                    f.write_str(&parts.join(" "))
+                }
+            }
+            TimeValue::Date { year, month, day } => write!(f, "{year:04}-{month:02}-{day:02}"),
+        }
+    }
+}
+
+/// known url conditions for `system:has url` and friends
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum UrlPredicate {
+    /// file has (or does not have) this exact url
+    Url { url: String, has: bool },
+    /// file has (or does not have) an url on this domain
+    Domain { domain: String, has: bool },
+    /// file has (or does not have) an url matching this regex
+    Regex { regex: String, has: bool },
+    /// file has (or does not have) an url matching this url class
+    UrlClass { class: String, has: bool },
+}
+
+impl fmt::Display for UrlPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlPredicate::Url { url, has: true } => write!(f, "has url {url}"),
+            UrlPredicate::Url { url, has: false } => write!(f, "does not have url {url}"),
+            UrlPredicate::Domain { domain, has: true } => write!(f, "has domain {domain}"),
+            UrlPredicate::Domain { domain, has: false } => {
+                write!(f, "does not have domain {domain}")
+            }
+            UrlPredicate::Regex { regex, has: true } => {
+                write!(f, "has url matching regex {regex}")
+            }
+            UrlPredicate::Regex { regex, has: false } => {
+                write!(f, "does not have a url matching regex {regex}")
+            }
+            UrlPredicate::UrlClass { class, has: true } => {
+                write!(f, "has a url with class {class}")
+            }
+            UrlPredicate::UrlClass { class, has: false } => {
+                write!(f, "does not have a url with url class {class}")
+            }
+        }
+    }
+}
+
+/// rating conditions, `service` is the rating service name
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum RatingPredicate {
+    /// file has been rated on the service
+    HasRating { service: String },
+    /// file has not been rated on the service
+    NoRating { service: String },
+    /// like/dislike services
+    Like { service: String, like: bool },
+    /// numerical services, `stars` out of `max_stars`
+    Stars {
+        service: String,
+        operator: Operator,
+        stars: u8,
+        max_stars: u8,
+    },
+    /// inc/dec services
+    Counter {
+        service: String,
+        operator: Operator,
+        value: u64,
+    },
+}
+
+impl fmt::Display for RatingPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RatingPredicate::HasRating { service } => write!(f, "has a rating for {service}"),
+            RatingPredicate::NoRating { service } => {
+                write!(f, "does not have a rating for {service}")
+            }
+            RatingPredicate::Like { service, like } => write!(
+                f,
+                "rating for {service} is {}",
+                if *like { "like" } else { "dislike" }
+            ),
+            RatingPredicate::Stars {
+                service,
+                operator,
+                stars,
+                max_stars,
+            } => write!(f, "rating for {service} {operator} {stars}/{max_stars}"),
+            RatingPredicate::Counter {
+                service,
+                operator,
+                value,
+            } => write!(f, "rating for {service} {operator} {value}"),
+        }
+    }
+}
+
+/// hydrus system predicates. the `Display` implementation renders the string hydrus expects
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum SystemPredicate {
+    Everything,
+    Inbox,
+    Archive,
+    HasDuration,
+    NoDuration,
+    HasAudio,
+    NoAudio,
+    HasTags,
+    Untagged,
+    HasNotes,
+    NoNotes,
+    NumberOfTags(Operator, u64),
+    Limit(u64),
+    Filesize(Operator, u64, SizeUnit),
+    Width(Operator, u64),
+    Height(Operator, u64),
+    NumPixels(Operator, u64, PixelUnit),
+    Ratio(RatioOperator, u32, u32),
+    Duration(Operator, u64, DurationUnit),
+    ImportTime(Operator, TimeValue),
+    ModifiedTime(Operator, TimeValue),
+    ArchivedTime(Operator, TimeValue),
+    LastViewedTime(Operator, TimeValue),
+    /// files with any of the hashes
+    Hash(Vec<String>, HashType),
+    /// files with any of the mimes, e.g. `image/png`
+    Filetype(Vec<String>),
+    Url(UrlPredicate),
+    Rating(RatingPredicate),
+}
+
+impl fmt::Display for SystemPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("system:")?;
+        match self {
+            SystemPredicate::Everything => f.write_str("everything"),
+            SystemPredicate::Inbox => f.write_str("inbox"),
+            SystemPredicate::Archive => f.write_str("archive"),
+            SystemPredicate::HasDuration => f.write_str("has duration"),
+            SystemPredicate::NoDuration => f.write_str("no duration"),
+            SystemPredicate::HasAudio => f.write_str("has audio"),
+            SystemPredicate::NoAudio => f.write_str("no audio"),
+            SystemPredicate::HasTags => f.write_str("has tags"),
+            SystemPredicate::Untagged => f.write_str("untagged"),
+            SystemPredicate::HasNotes => f.write_str("has notes"),
+            SystemPredicate::NoNotes => f.write_str("no notes"),
+            SystemPredicate::NumberOfTags(op, value) => write!(f, "number of tags {op} {value}"),
+            SystemPredicate::Limit(value) => write!(f, "limit = {value}"),
+            SystemPredicate::Filesize(op, value, unit) => write!(f, "filesize {op} {value} {unit}"),
+            SystemPredicate::Width(op, value) => write!(f, "width {op} {value}"),
+            SystemPredicate::Height(op, value) => write!(f, "height {op} {value}"),
+            SystemPredicate::NumPixels(op, value, unit) => {
+                write!(f, "num pixels {op} {value} {unit}")
+            }
+            SystemPredicate::Ratio(op, width, height) => write!(f, "ratio {op} {width}:{height}"),
+            SystemPredicate::Duration(op, value, unit) => write!(f, "duration {op} {value} {unit}"),
+            SystemPredicate::ImportTime(op, time) => write!(f, "import time {op} {time}"),
+            SystemPredicate::ModifiedTime(op, time) => write!(f, "modified date {op} {time}"),
+            SystemPredicate::ArchivedTime(op, time) => write!(f, "archived time {op} {time}"),
+            SystemPredicate::LastViewedTime(op, time) => {
+                write!(f, "last viewed time {op} {time}")
+            }
+            SystemPredicate::Hash(hashes, hash_type) => {
+                write!(f, "hash = {}", hashes.join(" "))?;
+                if *hash_type != HashType::Sha256 {
+                    write!(f, " {}", hash_type.as_str())?;
+                }
+                Ok(())
+            }
+            SystemPredicate::Filetype(mimes) => write!(f, "filetype = {}", mimes.join(", ")),
+            SystemPredicate::Url(predicate) => write!(f, "{predicate}"),
+            SystemPredicate::Rating(predicate) => write!(f, "{predicate}"),
+        }
+    }
+}
+
+/// file sort type for searches
+#[derive(PartialEq, Debug, Clone, Copy, Serialize_repr)]
+#[repr(u8)]
+pub enum FileSortType {
+    FileSize = 0,
+    Duration,
+    ImportTime,
+    FileType,
+    Random,
+    Width,
+    Height,
+    Ratio,
+    NumberOfPixels,
+    NumberOfTags,
+    NumberOfMediaViews,
+    TotalMediaViewtime,
+    ApproximateBitrate,
+    HasAudio,
+    ModifiedTime,
+    Framerate,
+    NumberOfFrames,
+    LastViewedTime = 18,
+    ArchiveTimestamp,
+    HashHex,
+    PixelHashHex,
+    Blurhash,
+}
+
+/// file sort direction for searches
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+/// identifiers returned by a search
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum SearchReturns {
+    #[default]
+    FileIds,
+    Hashes,
+    Both,
+}
+
+/// hydrus response after searching for files
+#[derive(Debug, Deserialize)]
+pub struct SearchFilesResponse {
+    #[serde(default)]
+    pub file_ids: Vec<u64>,
+    #[serde(default)]
+    pub hashes: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(predicate: SystemPredicate) -> String {
+        predicate.to_string()
+    }
+
+    #[test]
+    fn flag_predicates() {
+        assert_eq!(render(SystemPredicate::Everything), "system:everything");
+        assert_eq!(render(SystemPredicate::Inbox), "system:inbox");
+        assert_eq!(render(SystemPredicate::Archive), "system:archive");
+        assert_eq!(render(SystemPredicate::HasDuration), "system:has duration");
+        assert_eq!(render(SystemPredicate::NoDuration), "system:no duration");
+        assert_eq!(render(SystemPredicate::HasAudio), "system:has audio");
+        assert_eq!(render(SystemPredicate::NoAudio), "system:no audio");
+        assert_eq!(render(SystemPredicate::HasTags), "system:has tags");
+        assert_eq!(render(SystemPredicate::Untagged), "system:untagged");
+        assert_eq!(render(SystemPredicate::HasNotes), "system:has notes");
+        assert_eq!(render(SystemPredicate::NoNotes), "system:no notes");
+    }
+
+    #[test]
+    fn count_predicates() {
+        assert_eq!(
+            render(SystemPredicate::NumberOfTags(Operator::Greater, 5)),
+            "system:number of tags > 5"
+        );
+        assert_eq!(
+            render(SystemPredicate::NumberOfTags(Operator::Approximately, 10)),
+            "system:number of tags ~= 10"
+        );
+        assert_eq!(render(SystemPredicate::Limit(100)), "system:limit = 100");
+    }
+
+    #[test]
+    fn filesize_predicates() {
+        assert_eq!(
+            render(SystemPredicate::Filesize(
+                Operator::Greater,
+                10,
+                SizeUnit::Megabytes
+            )),
+            "system:filesize > 10 MB"
+        );
+        assert_eq!(
+            render(SystemPredicate::Filesize(
+                Operator::NotEqual,
+                0,
+                SizeUnit::Bytes
+            )),
+            "system:filesize \u{2260} 0 B"
+        );
+    }
+
+    #[test]
+    fn dimension_predicates() {
+        assert_eq!(
+            render(SystemPredicate::Width(Operator::Greater, 1000)),
+            "system:width > 1000"
+        );
+        assert_eq!(
+            render(SystemPredicate::Height(Operator::Equal, 600)),
+            "system:height = 600"
+        );
+        assert_eq!(
+            render(SystemPredicate::NumPixels(
+                Operator::Less,
+                1,
+                PixelUnit::Megapixels
+            )),
+            "system:num pixels < 1 megapixels"
+        );
+        assert_eq!(
+            render(SystemPredicate::Ratio(RatioOperator::WiderThan, 16, 9)),
+            "system:ratio wider than 16:9"
+        );
+        assert_eq!(
+            render(SystemPredicate::Ratio(RatioOperator::Equal, 1, 1)),
+            "system:ratio = 1:1"
+        );
+    }
+
+    #[test]
+    fn duration_predicates() {
+        assert_eq!(
+            render(SystemPredicate::Duration(
+                Operator::Less,
+                5,
+                DurationUnit::Seconds
+            )),
+            "system:duration < 5 seconds"
+        );
+        assert_eq!(
+            render(SystemPredicate::Duration(
+                Operator::Approximately,
+                600,
+                DurationUnit::Milliseconds
+            )),
+            "system:duration ~= 600 milliseconds"
+        );
+    }
+
+    #[test]
+    fn time_predicates() {
+        let ago = TimeValue::Ago {
+            years: 7,
+            months: 0,
+            days: 45,
+            hours: 1,
+        };
+        assert_eq!(
+            render(SystemPredicate::ImportTime(Operator::Less, ago)),
+            "system:import time < 7 years 45 days 1 hour"
+        );
+        let date = TimeValue::Date {
+            year: 2011,
+            month: 6,
+            day: 4,
+        };
+        assert_eq!(
+            render(SystemPredicate::ModifiedTime(Operator::Greater, date)),
+            "system:modified date > 2011-06-04"
+        );
+        let now = TimeValue::Ago {
+            years: 0,
+            months: 0,
+            days: 0,
+            hours: 0,
+        };
+        assert_eq!(
+            render(SystemPredicate::ArchivedTime(Operator::Greater, now)),
+            "system:archived time > 0 days"
+        );
+        assert_eq!(
+            render(SystemPredicate::LastViewedTime(
+                Operator::Approximately,
+                date
+            )),
+            "system:last viewed time ~= 2011-06-04"
+        );
+    }
+
+    #[test]
+    fn hash_predicates() {
+        let hashes = vec!["abcdef01".to_string(), "abcdef02".to_string()];
+        assert_eq!(
+            render(SystemPredicate::Hash(hashes.clone(), HashType::Sha256)),
+            "system:hash = abcdef01 abcdef02"
+        );
+        assert_eq!(
+            render(SystemPredicate::Hash(hashes, HashType::Md5)),
+            "system:hash = abcdef01 abcdef02 md5"
+        );
+    }
+
+    #[test]
+    fn filetype_predicates() {
+        assert_eq!(
+            render(SystemPredicate::Filetype(vec![
+                "image/jpg".to_string(),
+                "image/png".to_string()
+            ])),
+            "system:filetype = image/jpg, image/png"
+        );
+    }
+
+    #[test]
+    fn url_predicates() {
+        let url = "https://safebooru.donmai.us/posts/4695284".to_string();
+        assert_eq!(
+            render(SystemPredicate::Url(UrlPredicate::Url {
+                url: url.clone(),
+                has: true
+            })),
+            "system:has url https://safebooru.donmai.us/posts/4695284"
+        );
+        assert_eq!(
+            render(SystemPredicate::Url(UrlPredicate::Url { url, has: false })),
+            "system:does not have url https://safebooru.donmai.us/posts/4695284"
+        );
+        assert_eq!(
+            render(SystemPredicate::Url(UrlPredicate::Domain {
+                domain: "safebooru.com".to_string(),
+                has: false
+            })),
+            "system:does not have domain safebooru.com"
+        );
+        assert_eq!(
+            render(SystemPredicate::Url(UrlPredicate::Regex {
+                regex: "index\\.php".to_string(),
+                has: true
+            })),
+            "system:has url matching regex index\\.php"
+        );
+        assert_eq!(
+            render(SystemPredicate::Url(UrlPredicate::UrlClass {
+                class: "safebooru file page".to_string(),
+                has: true
+            })),
+            "system:has a url with class safebooru file page"
+        );
+        assert_eq!(
+            render(SystemPredicate::Url(UrlPredicate::UrlClass {
+                class: "safebooru file page".to_string(),
+                has: false
+            })),
+            "system:does not have a url with url class safebooru file page"
+        );
+    }
+
+    #[test]
+    fn rating_predicates() {
+        let service = "my favourites".to_string();
+        assert_eq!(
+            render(SystemPredicate::Rating(RatingPredicate::HasRating {
+                service: service.clone()
+            })),
+            "system:has a rating for my favourites"
+        );
+        assert_eq!(
+            render(SystemPredicate::Rating(RatingPredicate::NoRating {
+                service: service.clone()
+            })),
+            "system:does not have a rating for my favourites"
+        );
+        assert_eq!(
+            render(SystemPredicate::Rating(RatingPredicate::Like {
+                service: service.clone(),
+                like: true
+            })),
+            "system:rating for my favourites is like"
+        );
+        assert_eq!(
+            render(SystemPredicate::Rating(RatingPredicate::Stars {
+                service: service.clone(),
+                operator: Operator::Greater,
+                stars: 3,
+                max_stars: 5
+            })),
+            "system:rating for my favourites > 3/5"
+        );
+        assert_eq!(
+            render(SystemPredicate::Rating(RatingPredicate::Counter {
+                service,
+                operator: Operator::Equal,
+                value: 13
+            })),
+            "system:rating for my favourites = 13"
+        );
+    }
+
+    #[test]
+    fn builder_serializes_to_hydrus_tag_list() {
+        let tags = SearchBuilder::new()
+            .tag("blue eyes")
+            .exclude("character:foo")
+            .any_of(vec![
+                SearchTag::Tag("green eyes".into()),
+                SearchTag::System(SystemPredicate::Inbox),
+            ])
+            .system(SystemPredicate::Width(Operator::Greater, 1000))
+            .build();
+        assert_eq!(
+            serde_json::to_string(&tags).unwrap(),
+            r#"["blue eyes","-character:foo",["green eyes","system:inbox"],"system:width > 1000"]"#
+        );
+    }
+}
@@ -0,0 +1,1072 @@
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use super::*;
+
+/// kinds of problems found while parsing search text
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseErrorKind {
+    #[error("unterminated quote")]
+    UnterminatedQuote,
+    #[error("unclosed group")]
+    UnclosedGroup,
+    #[error("empty group")]
+    EmptyGroup,
+    #[error("empty alternative in group")]
+    EmptyAlternative,
+    #[error("groups cannot be nested")]
+    NestedGroup,
+    #[error("groups cannot be negated")]
+    NegatedGroup,
+    #[error("system predicates cannot be negated")]
+    NegatedSystemPredicate,
+    #[error("missing tag after negation")]
+    MissingTag,
+    #[error("unexpected character after term")]
+    TrailingCharacters,
+    #[error("expected OR between alternatives")]
+    MissingSeparator,
+    #[error("invalid system predicate")]
+    InvalidSystemPredicate,
+}
+
+/// search text parsing error. `position` is the byte offset of the problem in the input
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{kind} at position {position}")]
+pub struct ParseError {
+    pub position: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl FromStr for SystemPredicate {
+    type Err = ParseError;
+
+    /// parse the hydrus string of a system predicate, as rendered by `Display`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_system(s).ok_or(ParseError {
+            position: 0,
+            kind: ParseErrorKind::InvalidSystemPredicate,
+        })
+    }
+}
+
+fn parse_system(s: &str) -> Option<SystemPredicate> {
+    let body = s.strip_prefix("system:")?;
+    let predicate = match body {
+        "everything" => SystemPredicate::Everything,
+        "inbox" => SystemPredicate::Inbox,
+        "archive" => SystemPredicate::Archive,
+        "has duration" => SystemPredicate::HasDuration,
+        "no duration" => SystemPredicate::NoDuration,
+        "has audio" => SystemPredicate::HasAudio,
+        "no audio" => SystemPredicate::NoAudio,
+        "has tags" => SystemPredicate::HasTags,
+        "untagged" => SystemPredicate::Untagged,
+        "has notes" => SystemPredicate::HasNotes,
+        "no notes" => SystemPredicate::NoNotes,
+        _ => return parse_system_with_value(body),
+    };
+    Some(predicate)
+}
+
+fn parse_system_with_value(body: &str) -> Option<SystemPredicate> {
+    if let Some(rest) = body.strip_prefix("number of tags ") {
+        let (op, value) = parse_operator(rest)?;
+        return Some(SystemPredicate::NumberOfTags(op, parse_number(value)?));
+    }
+    if let Some(rest) = body.strip_prefix("limit = ") {
+        return Some(SystemPredicate::Limit(parse_number(rest)?));
+    }
+    if let Some(rest) = body.strip_prefix("filesize ") {
+        let (op, rest) = parse_operator(rest)?;
+        let (value, unit) = rest.split_once(' ')?;
+        let unit = match unit {
+            "B" => SizeUnit::Bytes,
+            "KB" => SizeUnit::Kilobytes,
+            "MB" => SizeUnit::Megabytes,
+            "GB" => SizeUnit::Gigabytes,
+            _ => return None,
+        };
+        return Some(SystemPredicate::Filesize(op, parse_number(value)?, unit));
+    }
+    if let Some(rest) = body.strip_prefix("width ") {
+        let (op, value) = parse_operator(rest)?;
+        return Some(SystemPredicate::Width(op, parse_number(value)?));
+    }
+    if let Some(rest) = body.strip_prefix("height ") {
+        let (op, value) = parse_operator(rest)?;
+        return Some(SystemPredicate::Height(op, parse_number(value)?));
+    }
+    if let Some(rest) = body.strip_prefix("num pixels ") {
+        let (op, rest) = parse_operator(rest)?;
+        let (value, unit) = rest.split_once(' ')?;
+        let unit = match unit {
+            "pixels" => PixelUnit::Pixels,
+            "kilopixels" => PixelUnit::Kilopixels,
+            "megapixels" => PixelUnit::Megapixels,
+            _ => return None,
+        };
+        return Some(SystemPredicate::NumPixels(op, parse_number(value)?, unit));
+    }
+    if let Some(rest) = body.strip_prefix("ratio ") {
+        let (op, rest) = if let Some(rest) = rest.strip_prefix("= ") {
+            (RatioOperator::Equal, rest)
+        } else if let Some(rest) = rest.strip_prefix("wider than ") {
+            (RatioOperator::WiderThan, rest)
+        } else {
+            (
+                RatioOperator::TallerThan,
+                rest.strip_prefix("taller than ")?,
+            )
+        };
+        let (width, height) = rest.split_once(':')?;
+        return Some(SystemPredicate::Ratio(
+            op,
+            parse_number(width)?,
+            parse_number(height)?,
+        ));
+    }
+    if let Some(rest) = body.strip_prefix("duration ") {
+        let (op, rest) = parse_operator(rest)?;
+        let (value, unit) = rest.split_once(' ')?;
+        let unit = match unit {
+            "milliseconds" => DurationUnit::Milliseconds,
+            "seconds" => DurationUnit::Seconds,
+            _ => return None,
+        };
+        return Some(SystemPredicate::Duration(op, parse_number(value)?, unit));
+    }
+    if let Some(rest) = body.strip_prefix("import time ") {
+        let (op, time) = parse_operator(rest)?;
+        return Some(SystemPredicate::ImportTime(op, parse_time(time)?));
+    }
+    if let Some(rest) = body.strip_prefix("modified date ") {
+        let (op, time) = parse_operator(rest)?;
+        return Some(SystemPredicate::ModifiedTime(op, parse_time(time)?));
+    }
+    if let Some(rest) = body.strip_prefix("archived time ") {
+        let (op, time) = parse_operator(rest)?;
+        return Some(SystemPredicate::ArchivedTime(op, parse_time(time)?));
+    }
+    if let Some(rest) = body.strip_prefix("last viewed time ") {
+        let (op, time) = parse_operator(rest)?;
+        return Some(SystemPredicate::LastViewedTime(op, parse_time(time)?));
+    }
+    if let Some(rest) = body.strip_prefix("hash = ") {
+        let mut hashes: Vec<&str> = rest.split(' ').collect();
+        let hash_type = match hashes.last() {
+            Some(&"md5") => Some(HashType::Md5),
+            Some(&"sha1") => Some(HashType::Sha1),
+            Some(&"sha512") => Some(HashType::Sha512),
+            _ => None,
+        };
+        if hash_type.is_some() {
+            hashes.pop();
+        }
+        if hashes.is_empty()
+            || hashes
+                .iter()
+                .any(|hash| hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return None;
+        }
+        return Some(SystemPredicate::Hash(
+            hashes.into_iter().map(String::from).collect(),
+            hash_type.unwrap_or(HashType::Sha256),
+        ));
+    }
+    if let Some(rest) = body.strip_prefix("filetype = ") {
+        let mimes: Vec<&str> = rest.split(", ").collect();
+        if mimes
+            .iter()
+            .any(|mime| mime.is_empty() || mime.contains(|c: char| c == ',' || c.is_whitespace()))
+        {
+            return None;
+        }
+        return Some(SystemPredicate::Filetype(
+            mimes.into_iter().map(String::from).collect(),
+        ));
+    }
+    if let Some(predicate) = parse_url(body) {
+        return Some(SystemPredicate::Url(predicate));
+    }
+    parse_rating(body).map(SystemPredicate::Rating)
+}
+
+fn parse_url(body: &str) -> Option<UrlPredicate> {
+    let value = |rest: &str| (!rest.is_empty()).then(|| rest.to_string());
+    if let Some(rest) = body.strip_prefix("has url matching regex ") {
+        return Some(UrlPredicate::Regex {
+            regex: value(rest)?,
+            has: true,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("does not have a url matching regex ") {
+        return Some(UrlPredicate::Regex {
+            regex: value(rest)?,
+            has: false,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("has a url with class ") {
+        return Some(UrlPredicate::UrlClass {
+            class: value(rest)?,
+            has: true,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("does not have a url with url class ") {
+        return Some(UrlPredicate::UrlClass {
+            class: value(rest)?,
+            has: false,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("has url ") {
+        return Some(UrlPredicate::Url {
+            url: value(rest)?,
+            has: true,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("does not have url ") {
+        return Some(UrlPredicate::Url {
+            url: value(rest)?,
+            has: false,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("has domain ") {
+        return Some(UrlPredicate::Domain {
+            domain: value(rest)?,
+            has: true,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("does not have domain ") {
+        return Some(UrlPredicate::Domain {
+            domain: value(rest)?,
+            has: false,
+        });
+    }
+    None
+}
+
+fn parse_rating(body: &str) -> Option<RatingPredicate> {
+    if let Some(service) = body.strip_prefix("has a rating for ") {
+        return (!service.is_empty()).then(|| RatingPredicate::HasRating {
+            service: service.to_string(),
+        });
+    }
+    if let Some(service) = body.strip_prefix("does not have a rating for ") {
+        return (!service.is_empty()).then(|| RatingPredicate::NoRating {
+            service: service.to_string(),
+        });
+    }
+    let rest = body.strip_prefix("rating for ")?;
+    if let Some(service) = rest.strip_suffix(" is like") {
+        return (!service.is_empty()).then(|| RatingPredicate::Like {
+            service: service.to_string(),
+            like: true,
+        });
+    }
+    if let Some(service) = rest.strip_suffix(" is dislike") {
+        return (!service.is_empty()).then(|| RatingPredicate::Like {
+            service: service.to_string(),
+            like: false,
+        });
+    }
+    let mut parts = rest.rsplitn(3, ' ');
+    let value = parts.next()?;
+    let operator = parse_operator_symbol(parts.next()?)?;
+    let service = parts.next().filter(|service| !service.is_empty())?;
+    if let Some((stars, max_stars)) = value.split_once('/') {
+        Some(RatingPredicate::Stars {
+            service: service.to_string(),
+            operator,
+            stars: parse_number(stars)?,
+            max_stars: parse_number(max_stars)?,
+        })
+    } else {
+        Some(RatingPredicate::Counter {
+            service: service.to_string(),
+            operator,
+            value: parse_number(value)?,
+        })
+    }
+}
+
+fn parse_operator_symbol(symbol: &str) -> Option<Operator> {
+    match symbol {
+        "<" => Some(Operator::Less),
+        "=" => Some(Operator::Equal),
+        "\u{2260}" => Some(Operator::NotEqual),
+        "~=" => Some(Operator::Approximately),
+        ">" => Some(Operator::Greater),
+        _ => None,
+    }
+}
+
+/// split `"<op> <rest>"`
+fn parse_operator(s: &str) -> Option<(Operator, &str)> {
+    let (symbol, rest) = s.split_once(' ')?;
+    Some((parse_operator_symbol(symbol)?, rest))
+}
+
+/// only canonical numbers are accepted, so printing gives back the same text
+fn parse_number<T: FromStr>(s: &str) -> Option<T> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) || (s.len() > 1 && s.starts_with('0'))
+    {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn parse_time(s: &str) -> Option<TimeValue> {
+    if let Some(date) = parse_date(s) {
+        return Some(date);
+    }
+    if s == "0 days" {
+        return Some(TimeValue::Ago {
+            years: 0,
+            months: 0,
+            days: 0,
+            hours: 0,
+        });
+    }
+    let words: Vec<&str> = s.split(' ').collect();
+    if !words.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut values = [0u32; 4];
+    let mut next_unit = 0;
+    for pair in words.chunks(2) {
+        let value: u32 = parse_number(pair[0])?;
+        let singular = pair[1].strip_suffix('s').unwrap_or(pair[1]);
+        if value == 0 || (value == 1) != (singular == pair[1]) {
+            return None;
+        }
+        let unit = ["year", "month", "day", "hour"]
+            .iter()
+            .position(|unit| *unit == singular)?;
+        if unit < next_unit {
+            return None;
+        }
+        values[unit] = value;
+        next_unit = unit + 1;
+    }
+    let [years, months, days, hours] = values;
+    Some(TimeValue::Ago {
+        years,
+        months,
+        days,
+        hours,
+    })
+}
+
+fn parse_date(s: &str) -> Option<TimeValue> {
+    let mut parts = s.split('-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some()
+        || year.len() != 4
+        || month.len() != 2
+        || day.len() != 2
+        || !s.chars().all(|c| c == '-' || c.is_ascii_digit())
+    {
+        return None;
+    }
+    Some(TimeValue::Date {
+        year: year.parse().ok()?,
+        month: month.parse().ok()?,
+        day: day.parse().ok()?,
+    })
+}
+
+/// the longest prefix of `span` ending on a word boundary which is a system predicate
+fn longest_predicate(span: &str) -> Option<(SystemPredicate, usize)> {
+    let mut ends: Vec<usize> = span
+        .char_indices()
+        .zip(span.chars().skip(1).map(Some).chain([None]))
+        .filter(|((_, c), next)| !c.is_whitespace() && next.is_none_or(char::is_whitespace))
+        .map(|((index, c), _)| index + c.len_utf8())
+        .collect();
+    while let Some(end) = ends.pop() {
+        if let Some(predicate) = parse_system(&span[..end]) {
+            return Some((predicate, end));
+        }
+    }
+    None
+}
+
+/// whether a term starting at the beginning of `s` ends an unquoted system predicate
+fn starts_term(s: &str) -> bool {
+    s.starts_with(['-', '(', '"']) || s.starts_with("system:")
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn error(&self, position: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError { position, kind }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// a term has to be followed by whitespace, the end of the input or, in groups, `)`
+    fn expect_boundary(&self, in_group: bool) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(c) if c.is_whitespace() || (in_group && c == ')') => Ok(()),
+            Some(_) => Err(self.error(self.pos, ParseErrorKind::TrailingCharacters)),
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<SearchQuery, ParseError> {
+        let mut tags = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            match self.peek() {
+                None => break,
+                Some('(') => {
+                    tags.push(SearchTag::Or(self.parse_group()?));
+                    self.expect_boundary(false)?;
+                }
+                Some('"') => {
+                    let text = self.parse_quoted()?;
+                    self.expect_boundary(false)?;
+                    tags.push(quoted_term(&text, start)?);
+                }
+                Some('-') => {
+                    self.pos += 1;
+                    tags.push(SearchTag::Negated(self.parse_negated(start, false)?));
+                }
+                Some(_) if self.rest().starts_with("system:") => {
+                    self.parse_system_span(&mut tags)?;
+                }
+                Some(_) => tags.push(SearchTag::Tag(TagPattern::new(self.parse_word()))),
+            }
+        }
+        Ok(SearchQuery { tags })
+    }
+
+    /// the tag after a `-`, which started at `start`
+    fn parse_negated(&mut self, start: usize, in_group: bool) -> Result<TagPattern, ParseError> {
+        match self.peek() {
+            None => Err(self.error(self.pos, ParseErrorKind::MissingTag)),
+            Some(c) if c.is_whitespace() || (in_group && c == ')') => {
+                Err(self.error(self.pos, ParseErrorKind::MissingTag))
+            }
+            Some('(') => Err(self.error(start, ParseErrorKind::NegatedGroup)),
+            Some('"') => {
+                let text = self.parse_quoted()?;
+                self.expect_boundary(in_group)?;
+                if text.starts_with("system:") {
+                    return Err(self.error(start, ParseErrorKind::NegatedSystemPredicate));
+                }
+                Ok(TagPattern::new(&text))
+            }
+            Some(_) if self.rest().starts_with("system:") => {
+                Err(self.error(start, ParseErrorKind::NegatedSystemPredicate))
+            }
+            Some(_) if in_group => {
+                let text = self.parse_group_text()?;
+                Ok(TagPattern::new(text))
+            }
+            Some(_) => Ok(TagPattern::new(self.parse_word())),
+        }
+    }
+
+    fn parse_word(&mut self) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        let mut text = String::new();
+        let mut chars = self.rest().char_indices().skip(1);
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos = start + index + 1;
+                    return Ok(text);
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                _ => text.push(c),
+            }
+        }
+        Err(self.error(start, ParseErrorKind::UnterminatedQuote))
+    }
+
+    /// an unquoted system predicate runs until the next term which clearly starts something
+    /// else. words after the longest valid predicate in that span are plain tags
+    fn parse_system_span(&mut self, tags: &mut Vec<SearchTag>) -> Result<(), ParseError> {
+        let start = self.pos;
+        let rest = self.rest();
+        let mut end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        while end < rest.len() {
+            let next = rest[end..].trim_start();
+            if next.is_empty() || starts_term(next) {
+                break;
+            }
+            let next_start = rest.len() - next.len();
+            end = next
+                .find(char::is_whitespace)
+                .map_or(rest.len(), |index| next_start + index);
+        }
+        let (predicate, length) = longest_predicate(&rest[..end])
+            .ok_or(self.error(start, ParseErrorKind::InvalidSystemPredicate))?;
+        tags.push(SearchTag::System(predicate));
+        tags.extend(
+            rest[length..end]
+                .split_whitespace()
+                .map(|word| SearchTag::Tag(TagPattern::new(word))),
+        );
+        self.pos += end;
+        Ok(())
+    }
+
+    fn parse_group(&mut self) -> Result<Vec<SearchTag>, ParseError> {
+        let open = self.pos;
+        self.pos += 1;
+        let mut alternatives = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Err(self.error(open, ParseErrorKind::UnclosedGroup)),
+                Some(')') if alternatives.is_empty() => {
+                    return Err(self.error(self.pos, ParseErrorKind::EmptyGroup));
+                }
+                Some(')') => return Err(self.error(self.pos, ParseErrorKind::EmptyAlternative)),
+                Some(_) => alternatives.push(self.parse_alternative()?),
+            }
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Err(self.error(open, ParseErrorKind::UnclosedGroup)),
+                Some(')') => {
+                    self.pos += 1;
+                    return Ok(alternatives);
+                }
+                Some(_) => {
+                    let rest = self.rest();
+                    match rest.strip_prefix("OR").and_then(|rest| rest.chars().next()) {
+                        Some(c) if c.is_whitespace() || c == ')' => self.pos += "OR".len(),
+                        _ => return Err(self.error(self.pos, ParseErrorKind::MissingSeparator)),
+                    }
+                }
+            }
+        }
+    }
+
+    fn parse_alternative(&mut self) -> Result<SearchTag, ParseError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(SearchTag::Negated(self.parse_negated(start, true)?))
+            }
+            Some('(') => Err(self.error(start, ParseErrorKind::NestedGroup)),
+            Some('"') => {
+                let text = self.parse_quoted()?;
+                self.expect_boundary(true)?;
+                quoted_term(&text, start)
+            }
+            _ => {
+                let text = self.parse_group_text()?;
+                if text.starts_with("system:") {
+                    let predicate = parse_system(text)
+                        .ok_or(self.error(start, ParseErrorKind::InvalidSystemPredicate))?;
+                    Ok(SearchTag::System(predicate))
+                } else {
+                    Ok(SearchTag::Tag(TagPattern::new(text)))
+                }
+            }
+        }
+    }
+
+    /// words up to the next `OR` separator or the end of the group
+    fn parse_group_text(&mut self) -> Result<&'a str, ParseError> {
+        let start = self.pos;
+        let mut end = start;
+        loop {
+            let rest = self.rest();
+            let length = rest
+                .find(|c: char| c.is_whitespace() || c == ')')
+                .unwrap_or(rest.len());
+            let word = &rest[..length];
+            if word == "OR" || word.is_empty() {
+                if end == start {
+                    return Err(self.error(self.pos, ParseErrorKind::EmptyAlternative));
+                }
+                self.pos = end;
+                return Ok(&self.input[start..end]);
+            }
+            self.pos += length;
+            end = self.pos;
+            self.skip_whitespace();
+        }
+    }
+}
+
+/// the content of a quoted term which started at `start`
+fn quoted_term(text: &str, start: usize) -> Result<SearchTag, ParseError> {
+    if text.starts_with("system:") {
+        parse_system(text).map(SearchTag::System).ok_or(ParseError {
+            position: start,
+            kind: ParseErrorKind::InvalidSystemPredicate,
+        })
+    } else {
+        Ok(SearchTag::Tag(TagPattern::new(text)))
+    }
+}
+
+impl FromStr for SearchQuery {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser { input: s, pos: 0 }.parse_query()
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn top_level_tag_safe(text: &str) -> bool {
+    !text.is_empty() && !text.contains(char::is_whitespace) && !starts_term(text)
+}
+
+fn top_level_system_safe(text: &str, predicate: &SystemPredicate) -> bool {
+    !text.split_whitespace().skip(1).any(starts_term)
+        && longest_predicate(text)
+            .is_some_and(|(parsed, end)| end == text.len() && parsed == *predicate)
+}
+
+fn group_text_safe(text: &str) -> bool {
+    !text.is_empty()
+        && text.trim() == text
+        && !text.contains(['(', ')'])
+        && !text.starts_with(['"', '-'])
+        && !text.split_whitespace().any(|word| word == "OR")
+}
+
+/// whether a group has no alternatives besides other empty groups. search text has no way to
+/// write such a group, so it is left out when printing
+fn is_empty_group(alternatives: &[SearchTag]) -> bool {
+    alternatives.iter().all(|alternative| match alternative {
+        SearchTag::Or(nested) => is_empty_group(nested),
+        _ => false,
+    })
+}
+
+fn write_group(f: &mut fmt::Formatter<'_>, alternatives: &[SearchTag]) -> fmt::Result {
+    f.write_str("(")?;
+    write_alternatives(f, alternatives, &mut true)?;
+    f.write_str(")")
+}
+
+/// nested groups are written inline, since groups cannot be nested in search text
+fn write_alternatives(
+    f: &mut fmt::Formatter<'_>,
+    alternatives: &[SearchTag],
+    first: &mut bool,
+) -> fmt::Result {
+    for alternative in alternatives {
+        if let SearchTag::Or(nested) = alternative {
+            write_alternatives(f, nested, first)?;
+            continue;
+        }
+        if !*first {
+            f.write_str(" OR ")?;
+        }
+        *first = false;
+        match alternative {
+            SearchTag::Tag(tag) => {
+                let text = tag.to_string();
+                if group_text_safe(&text) && !text.starts_with("system:") {
+                    f.write_str(&text)?;
+                } else {
+                    f.write_str(&quote(&text))?;
+                }
+            }
+            SearchTag::Negated(tag) => {
+                let text = tag.to_string();
+                if group_text_safe(&text) && !text.starts_with("system:") {
+                    write!(f, "-{text}")?;
+                } else {
+                    write!(f, "-{}", quote(&text))?;
+                }
+            }
+            SearchTag::System(predicate) => {
+                let text = predicate.to_string();
+                if group_text_safe(&text) && parse_system(&text).as_ref() == Some(predicate) {
+                    f.write_str(&text)?;
+                } else {
+                    f.write_str(&quote(&text))?;
+                }
+            }
+            SearchTag::Or(_) => {}
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for SearchQuery {
+    /// print the query as search text which parses back into the same query
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // text of an unquoted system predicate and the plain tags printed after it,
+        // with the length of the predicate
+        let mut span: Option<(String, usize)> = None;
+        let tags = self.tags.iter().filter(|tag| match tag {
+            SearchTag::Or(alternatives) => !is_empty_group(alternatives),
+            _ => true,
+        });
+        for (index, tag) in tags.enumerate() {
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+            match tag {
+                SearchTag::Tag(tag) => {
+                    let text = tag.to_string();
+                    let extends_span = span.as_mut().is_some_and(|(span, length)| {
+                        span.push(' ');
+                        span.push_str(&text);
+                        longest_predicate(span).is_some_and(|(_, end)| end > *length)
+                    });
+                    if top_level_tag_safe(&text) && !extends_span {
+                        f.write_str(&text)?;
+                    } else {
+                        f.write_str(&quote(&text))?;
+                        span = None;
+                    }
+                }
+                SearchTag::Negated(tag) => {
+                    let text = tag.to_string();
+                    if top_level_tag_safe(&text) {
+                        write!(f, "-{text}")?;
+                    } else {
+                        write!(f, "-{}", quote(&text))?;
+                    }
+                    span = None;
+                }
+                SearchTag::System(predicate) => {
+                    let text = predicate.to_string();
+                    if top_level_system_safe(&text, predicate) {
+                        f.write_str(&text)?;
+                        span = Some((text.clone(), text.len()));
+                    } else {
+                        f.write_str(&quote(&text))?;
+                        span = None;
+                    }
+                }
+                SearchTag::Or(alternatives) => {
+                    write_group(f, alternatives)?;
+                    span = None;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(text: &str) -> SearchQuery {
+        let query: SearchQuery = text.parse().unwrap();
+        assert_eq!(query.to_string(), text);
+        query
+    }
+
+    fn error(text: &str) -> ParseError {
+        text.parse::<SearchQuery>().unwrap_err()
+    }
+
+    #[test]
+    fn parses_mixed_query() {
+        let query = roundtrip("-character:foo system:width > 1000 (blue eyes OR green eyes)");
+        assert_eq!(
+            query.tags,
+            vec![
+                SearchTag::Negated(TagPattern {
+                    namespace: Some("character".to_string()),
+                    subtag: "foo".to_string(),
+                }),
+                SearchTag::System(SystemPredicate::Width(Operator::Greater, 1000)),
+                SearchTag::Or(vec![
+                    SearchTag::Tag("blue eyes".into()),
+                    SearchTag::Tag("green eyes".into()),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_namespaces_and_wildcards() {
+        let query = roundtrip("character:* sam* :) \"blue eyes\" -\"series:metroid prime\"");
+        let SearchTag::Tag(namespace_wildcard) = &query.tags[0] else {
+            panic!()
+        };
+        assert_eq!(namespace_wildcard.namespace.as_deref(), Some("character"));
+        assert!(namespace_wildcard.is_wildcard());
+        let SearchTag::Tag(wildcard) = &query.tags[1] else {
+            panic!()
+        };
+        assert!(wildcard.namespace.is_none() && wildcard.is_wildcard());
+        assert_eq!(query.tags[2], SearchTag::Tag(":)".into()));
+        assert_eq!(query.tags[3], SearchTag::Tag("blue eyes".into()));
+        assert_eq!(
+            query.tags[4],
+            SearchTag::Negated("series:metroid prime".into())
+        );
+    }
+
+    #[test]
+    fn parses_group_members() {
+        let query = roundtrip("(-blue eyes OR system:inbox OR \"a (b)\" OR system:has audio)");
+        assert_eq!(
+            query.tags,
+            vec![SearchTag::Or(vec![
+                SearchTag::Negated("blue eyes".into()),
+                SearchTag::System(SystemPredicate::Inbox),
+                SearchTag::Tag("a (b)".into()),
+                SearchTag::System(SystemPredicate::HasAudio),
+            ])]
+        );
+    }
+
+    #[test]
+    fn system_predicates_roundtrip() {
+        for text in [
+            "system:everything",
+            "system:no duration",
+            "system:untagged",
+            "system:number of tags ~= 10",
+            "system:limit = 100",
+            "system:filesize > 10 MB",
+            "system:height \u{2260} 600",
+            "system:num pixels < 2 kilopixels",
+            "system:ratio taller than 1:1",
+            "system:duration ~= 600 milliseconds",
+            "system:import time < 7 years 45 days 1 hour",
+            "system:modified date > 2011-06-04",
+            "system:archived time > 0 days",
+            "system:last viewed time < 1 month",
+            "system:hash = abcdef01 abcdef02 md5",
+            "system:filetype = image/jpg, image/png",
+            "system:does not have url https://example.com/post/1",
+            "system:has domain example.com",
+            "system:does not have a url matching regex index\\.php",
+            "system:rating for my stars > 3/5",
+            "system:rating for likes is dislike",
+            "system:rating for counter = 13",
+        ] {
+            let predicate: SystemPredicate = text.parse().unwrap();
+            assert_eq!(predicate.to_string(), text);
+            roundtrip(text);
+        }
+    }
+
+    #[test]
+    fn free_text_predicates_stop_at_next_term() {
+        let query = roundtrip("system:has a rating for my stars -foo system:inbox");
+        assert_eq!(
+            query.tags[0],
+            SearchTag::System(SystemPredicate::Rating(RatingPredicate::HasRating {
+                service: "my stars".to_string()
+            }))
+        );
+        assert_eq!(query.tags.len(), 3);
+    }
+
+    #[test]
+    fn tags_after_predicates() {
+        let query = roundtrip("system:width > 1000 1080p");
+        assert_eq!(query.tags[1], SearchTag::Tag("1080p".into()));
+
+        // an unquoted tag here would be read as part of the class name
+        let query = SearchQuery::from(vec![
+            SearchTag::System(SystemPredicate::Url(UrlPredicate::UrlClass {
+                class: "safebooru file page".to_string(),
+                has: true,
+            })),
+            SearchTag::Tag("blue".into()),
+        ]);
+        assert_eq!(
+            query.to_string(),
+            "system:has a url with class safebooru file page \"blue\""
+        );
+        assert_eq!(roundtrip(&query.to_string()), query);
+
+        // hex looking tags would be read as more hashes
+        let query = SearchQuery::from(vec![
+            SearchTag::System(SystemPredicate::Hash(
+                vec!["abcd".to_string()],
+                HashType::Sha256,
+            )),
+            SearchTag::Tag("cafe".into()),
+        ]);
+        assert_eq!(query.to_string(), "system:hash = abcd \"cafe\"");
+        assert_eq!(roundtrip(&query.to_string()), query);
+    }
+
+    #[test]
+    fn printing_quotes_when_needed() {
+        let query = SearchQuery::from(vec![
+            SearchTag::Tag("say \"hi\"".into()),
+            SearchTag::Or(vec![
+                SearchTag::Tag("a OR b".into()),
+                SearchTag::System(SystemPredicate::Url(UrlPredicate::Regex {
+                    regex: "(foo)".to_string(),
+                    has: true,
+                })),
+            ]),
+        ]);
+        let text = query.to_string();
+        assert_eq!(
+            text,
+            "\"say \\\"hi\\\"\" (\"a OR b\" OR \"system:has url matching regex (foo)\")"
+        );
+        assert_eq!(text.parse::<SearchQuery>().unwrap(), query);
+    }
+
+    #[test]
+    fn printing_leaves_out_empty_groups() {
+        let query = SearchQuery::from(vec![
+            SearchTag::Or(vec![]),
+            SearchTag::Tag("a".into()),
+            SearchTag::Or(vec![SearchTag::Or(vec![]), SearchTag::Or(vec![])]),
+            SearchTag::Or(vec![SearchTag::Or(vec![]), SearchTag::Tag("b".into())]),
+        ]);
+        assert_eq!(query.to_string(), "a (b)");
+        assert_eq!(
+            roundtrip(&query.to_string()).tags,
+            [
+                SearchTag::Tag("a".into()),
+                SearchTag::Or(vec![SearchTag::Tag("b".into())])
+            ]
+        );
+        assert_eq!(
+            SearchQuery::from(vec![SearchTag::Or(vec![])]).to_string(),
+            ""
+        );
+    }
+
+    #[test]
+    fn printing_flattens_nested_groups() {
+        let query = SearchQuery::from(vec![SearchTag::Or(vec![
+            SearchTag::Tag("a".into()),
+            SearchTag::Or(vec![
+                SearchTag::Tag("b".into()),
+                SearchTag::Or(vec![SearchTag::Negated("c".into())]),
+            ]),
+        ])]);
+        assert_eq!(query.to_string(), "(a OR b OR -c)");
+        assert_eq!(
+            roundtrip(&query.to_string()).tags,
+            [SearchTag::Or(vec![
+                SearchTag::Tag("a".into()),
+                SearchTag::Tag("b".into()),
+                SearchTag::Negated("c".into()),
+            ])]
+        );
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        assert_eq!(
+            error("foo \"bar"),
+            ParseError {
+                position: 4,
+                kind: ParseErrorKind::UnterminatedQuote
+            }
+        );
+        assert_eq!(
+            error("foo (a OR b"),
+            ParseError {
+                position: 4,
+                kind: ParseErrorKind::UnclosedGroup
+            }
+        );
+        assert_eq!(error("()").kind, ParseErrorKind::EmptyGroup);
+        assert_eq!(
+            error("(\"a\" b)"),
+            ParseError {
+                position: 5,
+                kind: ParseErrorKind::MissingSeparator
+            }
+        );
+        assert_eq!(
+            error("(a OR )"),
+            ParseError {
+                position: 6,
+                kind: ParseErrorKind::EmptyAlternative
+            }
+        );
+        assert_eq!(
+            error("(a OR (b))"),
+            ParseError {
+                position: 6,
+                kind: ParseErrorKind::NestedGroup
+            }
+        );
+        assert_eq!(
+            error("a -(b OR c)"),
+            ParseError {
+                position: 2,
+                kind: ParseErrorKind::NegatedGroup
+            }
+        );
+        assert_eq!(
+            error("a -system:inbox"),
+            ParseError {
+                position: 2,
+                kind: ParseErrorKind::NegatedSystemPredicate
+            }
+        );
+        assert_eq!(
+            error("a - b"),
+            ParseError {
+                position: 3,
+                kind: ParseErrorKind::MissingTag
+            }
+        );
+        assert_eq!(
+            error("\"a\"b"),
+            ParseError {
+                position: 3,
+                kind: ParseErrorKind::TrailingCharacters
+            }
+        );
+        assert_eq!(
+            error("blue system:width >> 5"),
+            ParseError {
+                position: 5,
+                kind: ParseErrorKind::InvalidSystemPredicate
+            }
+        );
+        assert_eq!(
+            error("system:import time < 1 years"),
+            ParseError {
+                position: 0,
+                kind: ParseErrorKind::InvalidSystemPredicate
+            }
+        );
+    }
+}
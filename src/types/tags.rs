@@ -0,0 +1,364 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::types::HydrusFile;
+
+/// a tag in the form hydrus stores it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tag {
+    pub namespace: Option<String>,
+    pub subtag: String,
+}
+
+impl Tag {
+    /// clean a tag the way hydrus does before storing it: lowercase it, collapse whitespace and
+    /// strip leading hyphens and `system:` from both namespace and subtag. tags left empty give
+    /// `None`
+    pub fn new(tag: &str) -> Option<Tag> {
+        let mut tag = tag.chars().take(1024).collect::<String>().to_lowercase();
+        // a single leading colon is part of the subtag, as in `:d`
+        if tag.starts_with(':') && !tag.starts_with("::") {
+            tag.insert(0, ':');
+        }
+
+        let (namespace, subtag) = if tag.contains(':') {
+            let tag = strip_gumpf(&tag);
+            match tag.split_once(':') {
+                Some((namespace, subtag)) => (strip_gumpf(namespace), strip_gumpf(subtag)),
+                None => (String::new(), tag),
+            }
+        } else {
+            (String::new(), strip_gumpf(&tag))
+        };
+
+        if subtag.is_empty() {
+            return None;
+        }
+        Some(Tag {
+            namespace: (!namespace.is_empty()).then_some(namespace),
+            subtag,
+        })
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{namespace}:{}", self.subtag),
+            // keep subtags starting with a colon from reading as a namespace
+            None if self.subtag.starts_with(':') => write!(f, ":{}", self.subtag),
+            None => write!(f, "{}", self.subtag),
+        }
+    }
+}
+
+/// collapse whitespace and strip leading whitespace, hyphens and `system:`
+fn strip_gumpf(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut rest = text.as_str();
+    loop {
+        rest = rest.trim_start_matches([' ', '-']);
+        match rest.strip_prefix("system:") {
+            Some(stripped) => rest = stripped,
+            None => return rest.to_string(),
+        }
+    }
+}
+
+/// a tag found by a tag search, with the number of files it is on
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TagCount {
+    #[serde(rename = "value")]
+    pub tag: String,
+    pub count: u64,
+}
+
+/// whether to search tags as stored, or as displayed after siblings and parents apply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagDisplayType {
+    #[default]
+    Storage,
+    Display,
+}
+
+impl TagDisplayType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagDisplayType::Storage => "storage",
+            TagDisplayType::Display => "display",
+        }
+    }
+}
+
+/// siblings and parents of a tag on one tag service
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TagRelations {
+    /// the sibling the tag is displayed as
+    pub ideal_tag: String,
+    /// all tags in the tag's sibling group, the tag and its ideal tag included
+    pub siblings: Vec<String>,
+    /// all children of the ideal tag
+    pub descendants: Vec<String>,
+    /// all parents of the ideal tag
+    pub ancestors: Vec<String>,
+}
+
+/// hydrus response for sibling and parent lookups
+#[derive(Debug, Deserialize)]
+pub(crate) struct SiblingsAndParentsResponse {
+    /// relations by tag, then by tag service key
+    pub tags: HashMap<String, HashMap<String, TagRelations>>,
+}
+
+/// tags looked up per `add_tags/get_siblings_and_parents` request
+pub(crate) const SIBLINGS_AND_PARENTS_BATCH_SIZE: usize = 100;
+
+/// resolves tags to the tags hydrus displays for them on one tag service, using relations
+/// loaded beforehand
+#[derive(Debug, Clone, Default)]
+pub struct TagResolver {
+    ideal_tags: HashMap<String, String>,
+    ancestors: HashMap<String, Vec<String>>,
+}
+
+impl TagResolver {
+    /// build a resolver from looked up relations, keeping those of `tag_service_key`
+    pub fn new(
+        tag_service_key: &str,
+        relations: &HashMap<String, HashMap<String, TagRelations>>,
+    ) -> TagResolver {
+        let mut resolver = TagResolver::default();
+        for (tag, services) in relations {
+            let Some(relations) = services.get(tag_service_key) else {
+                continue;
+            };
+            for sibling in relations.siblings.iter().chain([tag]) {
+                resolver
+                    .ideal_tags
+                    .insert(sibling.to_owned(), relations.ideal_tag.to_owned());
+            }
+            resolver.ancestors.insert(
+                relations.ideal_tag.to_owned(),
+                relations.ancestors.to_owned(),
+            );
+        }
+        resolver
+    }
+
+    /// the sibling a tag is displayed as. unknown tags are their own ideal tag
+    pub fn ideal_tag<'a>(&'a self, tag: &'a str) -> &'a str {
+        self.ideal_tags.get(tag).map_or(tag, String::as_str)
+    }
+
+    /// parents implied by a tag, through its ideal tag
+    pub fn parents(&self, tag: &str) -> &[String] {
+        self.ancestors
+            .get(self.ideal_tag(tag))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// the tags displayed for a file with `tags`: their ideal tags and all implied parents
+    pub fn resolve<'a>(&self, tags: impl IntoIterator<Item = &'a str>) -> BTreeSet<String> {
+        let mut resolved = BTreeSet::new();
+        for tag in tags {
+            resolved.insert(self.ideal_tag(tag).to_string());
+            resolved.extend(self.parents(tag).iter().cloned());
+        }
+        resolved
+    }
+}
+
+/// a change to the user's favourite tags
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FavouriteTagsChange {
+    /// replace all favourite tags
+    Set(Vec<String>),
+    Add(Vec<String>),
+    Remove(Vec<String>),
+}
+
+/// a change to a file's tags on a tag service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagAction {
+    /// add a tag, local tag services only
+    Add(String),
+    /// delete a tag, local tag services only
+    Delete(String),
+    /// pend a tag, tag repositories only
+    Pend(String),
+    RescindPend(String),
+    /// petition a tag's removal, tag repositories only
+    Petition {
+        tag: String,
+        reason: String,
+    },
+    RescindPetition(String),
+}
+
+impl TagAction {
+    fn code(&self) -> u8 {
+        match self {
+            TagAction::Add(_) => 0,
+            TagAction::Delete(_) => 1,
+            TagAction::Pend(_) => 2,
+            TagAction::RescindPend(_) => 3,
+            TagAction::Petition { .. } => 4,
+            TagAction::RescindPetition(_) => 5,
+        }
+    }
+
+    fn value(&self) -> Value {
+        match self {
+            TagAction::Petition { tag, reason } => json!([tag, reason]),
+            TagAction::Add(tag)
+            | TagAction::Delete(tag)
+            | TagAction::Pend(tag)
+            | TagAction::RescindPend(tag)
+            | TagAction::RescindPetition(tag) => json!(tag),
+        }
+    }
+}
+
+/// group actions into hydrus' action code to tags map
+pub(crate) fn actions_to_tags(actions: &[TagAction]) -> BTreeMap<String, Vec<Value>> {
+    let mut grouped: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for action in actions {
+        grouped
+            .entry(action.code().to_string())
+            .or_default()
+            .push(action.value());
+    }
+    grouped
+}
+
+/// Payload for editing tags
+#[derive(Debug, Serialize)]
+pub(crate) struct AddTagsRequest {
+    #[serde(flatten)]
+    pub file: HydrusFile,
+    pub service_keys_to_actions_to_tags: HashMap<String, BTreeMap<String, Vec<Value>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_previously_deleted_mappings: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create_new_deleted_mappings: Option<bool>,
+}
+
+impl AddTagsRequest {
+    pub(crate) fn new(
+        file: HydrusFile,
+        actions: &HashMap<String, Vec<TagAction>>,
+        override_previously_deleted_mappings: Option<bool>,
+        create_new_deleted_mappings: Option<bool>,
+    ) -> AddTagsRequest {
+        AddTagsRequest {
+            file,
+            service_keys_to_actions_to_tags: actions
+                .iter()
+                .map(|(key, actions)| (key.to_owned(), actions_to_tags(actions)))
+                .collect(),
+            override_previously_deleted_mappings,
+            create_new_deleted_mappings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // hand-written cases following the cleaning rules of hydrus, not captured responses.
+    // the live clean_tags tests check them against a real client
+    #[test]
+    fn cleans_hand_written_cases() {
+        let fixtures: Vec<Value> =
+            serde_json::from_str(include_str!("../../tests/fixtures/clean_tags_cases.json"))
+                .unwrap();
+        for fixture in fixtures {
+            let tag = fixture["tag"].as_str().unwrap();
+            let clean = Tag::new(tag).map(|tag| tag.to_string());
+            assert_eq!(clean.as_deref(), fixture["clean"].as_str(), "{tag:?}");
+        }
+    }
+
+    #[test]
+    fn resolves_siblings_and_parents() {
+        let response: SiblingsAndParentsResponse = serde_json::from_value(json!({
+            "services": {},
+            "tags": {
+                "samus_aran": {
+                    "my tags": {
+                        "ideal_tag": "character:samus aran",
+                        "siblings": ["samus_aran", "character:samus aran"],
+                        "descendants": [],
+                        "ancestors": ["series:metroid", "gender:female"],
+                    },
+                    "other tags": {
+                        "ideal_tag": "samus_aran",
+                        "siblings": ["samus_aran"],
+                        "descendants": [],
+                        "ancestors": [],
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let resolver = TagResolver::new("my tags", &response.tags);
+        assert_eq!(
+            resolver.ideal_tag("character:samus aran"),
+            "character:samus aran"
+        );
+        assert_eq!(resolver.parents("samus_aran").len(), 2);
+        assert_eq!(
+            resolver.resolve(["samus_aran", "blue eyes"]),
+            BTreeSet::from(
+                [
+                    "blue eyes",
+                    "character:samus aran",
+                    "gender:female",
+                    "series:metroid"
+                ]
+                .map(String::from)
+            )
+        );
+    }
+
+    #[test]
+    fn serializes_actions_by_code() {
+        let actions = HashMap::from([(
+            "6c6f63616c2074616773".to_string(),
+            vec![
+                TagAction::Add("character:samus aran".to_string()),
+                TagAction::Petition {
+                    tag: "typo".to_string(),
+                    reason: "misspelled".to_string(),
+                },
+                TagAction::Add("blue eyes".to_string()),
+            ],
+        )]);
+        let request = AddTagsRequest::new(
+            HydrusFile::Hash("ad6d3599".to_string()),
+            &actions,
+            None,
+            Some(false),
+        );
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "hash": "ad6d3599",
+                "service_keys_to_actions_to_tags": {
+                    "6c6f63616c2074616773": {
+                        "0": ["character:samus aran", "blue eyes"],
+                        "4": [["typo", "misspelled"]],
+                    }
+                },
+                "create_new_deleted_mappings": false,
+            })
+        );
+    }
+}
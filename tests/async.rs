@@ -1,6 +1,12 @@
+use futures_util::lock::{Mutex, MutexGuard};
 use hydrus_api::async_lib::{client::*, traits::*};
+use hydrus_api::types::duplicates::*;
+use hydrus_api::types::search::*;
+use hydrus_api::types::tags::*;
 use hydrus_api::types::*;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::LazyLock;
 
 fn init_client() -> HydrusClient {
     let keyfile = PathBuf::from("secrets");
@@ -10,6 +16,60 @@ fn init_client() -> HydrusClient {
     client
 }
 
+async fn first_hash(client: &HydrusClient) -> String {
+    client
+        .search_files(
+            &[SearchTag::System(SystemPredicate::Limit(1))],
+            None,
+            None,
+            None,
+            None,
+            SearchReturns::Hashes,
+        )
+        .await
+        .unwrap()
+        .hashes
+        .remove(0)
+}
+
+/// tests that change file data work on ./assets/img1.png, one at a time
+static TEST_FILE: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+/// import the test file and return its hash, along with the guard that keeps other tests off it
+async fn import_test_file(client: &HydrusClient) -> (MutexGuard<'static, ()>, String) {
+    let guard = TEST_FILE.lock().await;
+    let path = PathBuf::from("./assets/img1.png").canonicalize().unwrap();
+    let hash = client
+        .add_file_via_path(path, None, None)
+        .await
+        .unwrap()
+        .hash;
+    (guard, hash)
+}
+
+/// delete the test file from every local domain and clear its deletion record
+async fn delete_test_file(client: &HydrusClient, hash: String) {
+    let all_local_files = Some(FileDomain::FileServiceKey(
+        client
+            .get_service_name("all local files")
+            .await
+            .unwrap()
+            .service_key,
+    ));
+    client
+        .delete_files(HydrusFile::Hash(hash.clone()), None, None)
+        .await
+        .unwrap();
+    client
+        .delete_files(HydrusFile::Hash(hash.clone()), all_local_files, None)
+        .await
+        .unwrap();
+    client
+        .clear_file_deletion_records(HydrusFile::Hash(hash))
+        .await
+        .unwrap();
+}
+
 #[tokio::test]
 async fn test_service_name_info() {
     let client = init_client();
@@ -35,13 +95,10 @@ async fn test_get_services() {
 #[tokio::test]
 async fn add_and_delete_file() {
     let client = init_client();
+    let (_guard, hash) = import_test_file(&client).await;
     let path = PathBuf::from("./assets/img1.png").canonicalize().unwrap();
-    let file = client
-        .add_file_via_path(path.clone(), None, None)
-        .await
-        .unwrap();
     if client
-        .add_file_via_path(path.clone(), None, None)
+        .add_file_via_path(path, None, None)
         .await
         .unwrap()
         .status
@@ -50,25 +107,381 @@ async fn add_and_delete_file() {
         panic!();
     }
 
-    let all_local_files = Some(FileDomain::FileServiceKey(
+    delete_test_file(&client, hash).await;
+}
+
+#[tokio::test]
+async fn test_search_files() {
+    let client = init_client();
+    let res = client
+        .search_files(
+            &[SearchTag::System(SystemPredicate::Everything)],
+            None,
+            None,
+            Some(FileSortType::ImportTime),
+            Some(SortDirection::Descending),
+            SearchReturns::Both,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.file_ids.len(), res.hashes.len())
+}
+
+#[tokio::test]
+async fn test_file_metadata() {
+    let client = init_client();
+    let search = client
+        .search_files(
+            &[SearchTag::System(SystemPredicate::Limit(5))],
+            None,
+            None,
+            None,
+            None,
+            SearchReturns::Hashes,
+        )
+        .await
+        .unwrap();
+    let options = FileMetadataOptions {
+        include_notes: true,
+        include_blurhash: true,
+        ..Default::default()
+    };
+    let res = client
+        .file_metadata(HydrusFile::Hashes(search.hashes.clone()), options)
+        .await
+        .unwrap();
+    assert_eq!(res.len(), search.hashes.len())
+}
+
+#[tokio::test]
+async fn test_download_to_path() {
+    let client = init_client();
+    let hash = first_hash(&client).await;
+    let path = client
+        .download_to_path(SingleFile::Hash(hash), std::env::temp_dir(), None)
+        .await
+        .unwrap();
+    assert!(path.starts_with(std::env::temp_dir()));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_get_thumbnail() {
+    let mut client = init_client();
+    let dir = std::env::temp_dir().join("hydrus_api_thumbnails_async");
+    client
+        .set_thumbnail_cache(hydrus_api::cache::ThumbnailCache::new(dir.clone(), 1 << 20).unwrap());
+    let file = SingleFile::Hash(first_hash(&client).await);
+    let first = client.get_thumbnail(file.clone(), true).await.unwrap();
+    let second = client.get_thumbnail(file, true).await.unwrap();
+    if let (Thumbnail::Image { bytes: a, .. }, Thumbnail::Image { bytes: b, .. }) = (first, second)
+    {
+        assert_eq!(a, b);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_render_file() {
+    let client = init_client();
+    let search = client
+        .search_files(
+            &[
+                SearchTag::System(SystemPredicate::Filetype(vec!["image".to_string()])),
+                SearchTag::System(SystemPredicate::Limit(1)),
+            ],
+            None,
+            None,
+            None,
+            None,
+            SearchReturns::Hashes,
+        )
+        .await
+        .unwrap();
+    let options = RenderOptions {
+        format: Some(RenderFormat::Png),
+        ..Default::default()
+    };
+    let rendered = client
+        .render_file(SingleFile::Hash(search.hashes[0].clone()), options)
+        .await
+        .unwrap();
+    assert_eq!(rendered.mime, "image/png");
+}
+
+#[tokio::test]
+async fn test_local_file_access() {
+    use futures_util::TryStreamExt;
+
+    let client = init_client();
+    let locations = client.local_file_storage_locations().await.unwrap();
+    assert!(!locations.is_empty());
+    let file = SingleFile::Hash(first_hash(&client).await);
+    let path = client.file_path(file.clone()).await.unwrap();
+    let access = LocalFileAccess::new(&client).await.unwrap();
+    assert!(access.is_local());
+    let size = access
+        .open(file)
+        .await
+        .unwrap()
+        .body
+        .try_fold(
+            0,
+            |size, bytes| async move { Ok(size + bytes.len() as u64) },
+        )
+        .await
+        .unwrap();
+    assert_eq!(size, path.size);
+}
+
+#[tokio::test]
+async fn test_file_hashes() {
+    let client = init_client();
+    let hash = first_hash(&client).await;
+    let md5 = client
+        .file_hashes(std::slice::from_ref(&hash), HashType::Sha256, HashType::Md5)
+        .await
+        .unwrap();
+    let sha256 = client
+        .file_hashes(
+            &md5.values().cloned().collect::<Vec<_>>(),
+            HashType::Md5,
+            HashType::Sha256,
+        )
+        .await
+        .unwrap();
+    assert!(sha256.values().all(|converted| *converted == hash));
+}
+
+#[tokio::test]
+async fn test_add_tags() {
+    let client = init_client();
+    let service = client.get_service_name("my tags").await.unwrap();
+    let (_guard, hash) = import_test_file(&client).await;
+    let file = HydrusFile::Hash(hash.clone());
+    for action in [TagAction::Add, TagAction::Delete] {
+        let actions = HashMap::from([(
+            service.service_key.clone(),
+            vec![action("hydrus_api test".to_string())],
+        )]);
         client
-            .get_service_name("all local files")
+            .add_tags(file.clone(), actions, None, None)
             .await
-            .unwrap()
-            .service_key,
+            .unwrap();
+    }
+    delete_test_file(&client, hash).await;
+}
+
+#[tokio::test]
+async fn test_clean_tags() {
+    let client = init_client();
+    // the hand-written cases the local cleaning is tested against
+    let fixtures: Vec<serde_json::Value> =
+        serde_json::from_str(include_str!("fixtures/clean_tags_cases.json")).unwrap();
+    for fixture in fixtures {
+        let tag = fixture["tag"].as_str().unwrap().to_string();
+        let clean = client.clean_tags(std::slice::from_ref(&tag)).await.unwrap();
+        let local = Tag::new(&tag).map(|tag| tag.to_string());
+        assert_eq!(clean.first(), local.as_ref(), "{tag:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_search_tags() {
+    let mut client = init_client();
+    client.set_tag_search_cache(hydrus_api::cache::TagSearchCache::new(
+        8,
+        std::time::Duration::from_secs(60),
     ));
+    let tags = client
+        .search_tags("bl", None, None, TagDisplayType::Display)
+        .await
+        .unwrap();
+    let mut narrowed = client
+        .search_tags("blu", None, None, TagDisplayType::Display)
+        .await
+        .unwrap();
+    assert!(narrowed.iter().all(|tag| tags.contains(tag)));
+    // the narrowed result must match what the client itself returns
+    let mut uncached = init_client()
+        .search_tags("blu", None, None, TagDisplayType::Display)
+        .await
+        .unwrap();
+    narrowed.sort_by(|a, b| a.tag.cmp(&b.tag));
+    uncached.sort_by(|a, b| a.tag.cmp(&b.tag));
+    assert_eq!(narrowed, uncached);
+}
+
+#[tokio::test]
+async fn test_tag_resolver() {
+    let client = init_client();
+    let service = client.get_service_name("my tags").await.unwrap();
+    let tags = vec!["blue eyes".to_string()];
+    let relations = client.get_siblings_and_parents(&tags).await.unwrap();
+    assert!(relations["blue eyes"].contains_key(&service.service_key));
+    let resolver = client
+        .tag_resolver(&tags, &service.service_key)
+        .await
+        .unwrap();
+    let ideal = resolver.ideal_tag("blue eyes").to_string();
+    assert!(resolver.resolve(["blue eyes"]).contains(&ideal));
+}
+
+#[tokio::test]
+async fn test_favourite_tags() {
+    let client = init_client();
+    let tag = "hydrus_api favourite".to_string();
+    let favourites = client
+        .set_favourite_tags(FavouriteTagsChange::Add(vec![tag.clone()]))
+        .await
+        .unwrap();
+    assert!(favourites.contains(&tag));
+    assert_eq!(client.get_favourite_tags().await.unwrap(), favourites);
+    let favourites = client
+        .set_favourite_tags(FavouriteTagsChange::Remove(vec![tag.clone()]))
+        .await
+        .unwrap();
+    assert!(!favourites.contains(&tag));
+}
+
+#[tokio::test]
+async fn test_associate_url() {
+    let client = init_client();
+    let file = HydrusFile::Hash(first_hash(&client).await);
+    let url = "https://example.com/hydrus_api".to_string();
+    client
+        .associate_url(file.clone(), vec![url.clone()], vec![], None)
+        .await
+        .unwrap();
+    client
+        .associate_url(file, vec![], vec![url], None)
+        .await
+        .unwrap();
+}
 
+#[tokio::test]
+async fn test_get_url_info() {
+    let client = init_client();
+    let info = client
+        .get_url_info("https://example.com/hydrus_api")
+        .await
+        .unwrap();
+    assert_eq!(info.url_type, UrlType::Unknown);
+}
+
+#[tokio::test]
+async fn test_notes() {
+    let client = init_client();
+    let file = SingleFile::Hash(first_hash(&client).await);
+    let notes = HashMap::from([("hydrus_api".to_string(), "test note".to_string())]);
+    let merge = NoteMerge::Clever {
+        extend_existing_note_if_possible: true,
+        conflict_resolution: NoteConflict::Rename,
+    };
+    let stored = client
+        .set_notes(file.clone(), notes.clone(), merge)
+        .await
+        .unwrap();
+    assert_eq!(stored, notes);
     client
-        .delete_files(HydrusFile::Hash(file.hash.clone()), None, None)
+        .delete_notes(file, vec!["hydrus_api".to_string()])
         .await
         .unwrap();
+}
+
+#[tokio::test]
+async fn test_set_rating() {
+    let client = init_client();
+    let services = client.get_services().await.unwrap();
+    let Some(service) = services
+        .into_values()
+        .find(|service| service.r#type == ServiceType::BoolRating)
+    else {
+        return;
+    };
+    let (_guard, hash) = import_test_file(&client).await;
+    let file = SingleFile::Hash(hash.clone());
+    assert!(
+        client
+            .set_rating(file.clone(), &service, RatingValue::Stars(1))
+            .await
+            .is_err()
+    );
     client
-        .delete_files(HydrusFile::Hash(file.hash.clone()), all_local_files, None)
+        .set_rating(file.clone(), &service, RatingValue::Like)
+        .await
+        .unwrap();
+    let metadata = client
+        .file_metadata(file.into(), FileMetadataOptions::default())
         .await
         .unwrap();
+    assert_eq!(
+        metadata[0].ratings.get(&service.service_key),
+        Some(&Some(FileRating::Boolean(true)))
+    );
+    delete_test_file(&client, hash).await;
+}
 
+#[tokio::test]
+async fn test_file_times() {
+    let client = init_client();
+    let (_guard, hash) = import_test_file(&client).await;
+    let file = SingleFile::Hash(hash.clone());
+    client
+        .set_time(
+            file.clone(),
+            TimestampType::ModifiedOnDisk,
+            Some(1_500_000_000_123),
+        )
+        .await
+        .unwrap();
     client
-        .clear_file_deletion_records(HydrusFile::Hash(file.hash))
+        .increment_file_viewtime(file.clone(), CanvasType::ClientApi, 1.5, None, None)
         .await
         .unwrap();
+    let options = FileMetadataOptions {
+        include_milliseconds: true,
+        ..Default::default()
+    };
+    let metadata = client.file_metadata(file.into(), options).await.unwrap();
+    let modified = metadata[0].time_modified_details["local"];
+    assert_eq!((modified * 1000.0).round() as u64, 1_500_000_000_123);
+    delete_test_file(&client, hash).await;
+}
+
+#[tokio::test]
+async fn test_get_file_relationships() {
+    let client = init_client();
+    let hash = first_hash(&client).await;
+    let relationships = client
+        .get_file_relationships(HydrusFile::Hash(hash.clone()), None)
+        .await
+        .unwrap();
+    assert!(relationships.contains_key(&hash));
+}
+
+#[tokio::test]
+async fn test_potentials() {
+    let client = init_client();
+    let search = PotentialsSearch {
+        pixel_duplicates: PixelDuplicates::Required,
+        ..Default::default()
+    };
+    let count = client.get_potentials_count(&search).await.unwrap();
+    let pairs = client.get_potential_pairs(&search, Some(5)).await.unwrap();
+    assert!(pairs.len() as u64 <= count.min(5));
+    let random = client.get_random_potentials(&search).await.unwrap();
+    assert_eq!(random.is_empty(), count == 0);
+}
+
+#[tokio::test]
+async fn test_resolve_duplicates_dry_run() {
+    use hydrus_api::rules::*;
+
+    let client = init_client();
+    let mut rules = DuplicateRules::new(vec![Box::new(HigherResolution), Box::new(LargerFilesize)]);
+    rules.max_pairs = Some(10);
+    let report = client.resolve_duplicates(&rules).await.unwrap();
+    assert!(!report.applied);
+    assert!(report.resolutions.len() + report.skipped.len() <= 10);
 }
@@ -1,6 +1,12 @@
 use hydrus_api::sync_lib::client::HydrusClient;
 use hydrus_api::sync_lib::traits::*;
+use hydrus_api::types::duplicates::*;
+use hydrus_api::types::search::*;
+use hydrus_api::types::tags::*;
+use hydrus_api::types::*;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
 
 fn init_client() -> HydrusClient {
     let keyfile = PathBuf::from("secrets");
@@ -10,6 +16,51 @@ fn init_client() -> HydrusClient {
     client
 }
 
+fn first_hash(client: &HydrusClient) -> String {
+    client
+        .search_files(
+            &[SearchTag::System(SystemPredicate::Limit(1))],
+            None,
+            None,
+            None,
+            None,
+            SearchReturns::Hashes,
+        )
+        .unwrap()
+        .hashes
+        .remove(0)
+}
+
+/// tests that change file data work on ./assets/img1.png, one at a time
+static TEST_FILE: Mutex<()> = Mutex::new(());
+
+/// import the test file and return its hash, along with the guard that keeps other tests off it
+fn import_test_file(client: &HydrusClient) -> (MutexGuard<'static, ()>, String) {
+    let guard = TEST_FILE.lock().unwrap_or_else(PoisonError::into_inner);
+    let path = PathBuf::from("./assets/img1.png").canonicalize().unwrap();
+    let hash = client.add_file_via_path(path, None, None).unwrap().hash;
+    (guard, hash)
+}
+
+/// delete the test file from every local domain and clear its deletion record
+fn delete_test_file(client: &HydrusClient, hash: String) {
+    let all_local_files = Some(FileDomain::FileServiceKey(
+        client
+            .get_service_name("all local files")
+            .unwrap()
+            .service_key,
+    ));
+    client
+        .delete_files(HydrusFile::Hash(hash.clone()), None, None)
+        .unwrap();
+    client
+        .delete_files(HydrusFile::Hash(hash.clone()), all_local_files, None)
+        .unwrap();
+    client
+        .clear_file_deletion_records(HydrusFile::Hash(hash))
+        .unwrap();
+}
+
 #[test]
 fn test_service_name_info() {
     let client = init_client();
@@ -30,3 +81,342 @@ fn test_get_services() {
     let res = client.get_services().unwrap();
     assert!(!res.is_empty())
 }
+
+#[test]
+fn test_search_files() {
+    let client = init_client();
+    let res = client
+        .search_files(
+            &[SearchTag::System(SystemPredicate::Everything)],
+            None,
+            None,
+            Some(FileSortType::ImportTime),
+            Some(SortDirection::Descending),
+            SearchReturns::Both,
+        )
+        .unwrap();
+    assert_eq!(res.file_ids.len(), res.hashes.len())
+}
+
+#[test]
+fn test_file_metadata() {
+    let client = init_client();
+    let search = client
+        .search_files(
+            &[SearchTag::System(SystemPredicate::Limit(5))],
+            None,
+            None,
+            None,
+            None,
+            SearchReturns::Hashes,
+        )
+        .unwrap();
+    let options = FileMetadataOptions {
+        include_notes: true,
+        include_blurhash: true,
+        ..Default::default()
+    };
+    let res = client
+        .file_metadata(HydrusFile::Hashes(search.hashes.clone()), options)
+        .unwrap();
+    assert_eq!(res.len(), search.hashes.len())
+}
+
+#[test]
+fn test_download_to_path() {
+    let client = init_client();
+    let hash = first_hash(&client);
+    let path = client
+        .download_to_path(
+            SingleFile::Hash(hash),
+            std::env::temp_dir(),
+            Some(&|progress: DownloadProgress| {
+                assert!(progress.total.is_none_or(|total| progress.done <= total))
+            }),
+        )
+        .unwrap();
+    assert!(path.starts_with(std::env::temp_dir()));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_get_thumbnail() {
+    let mut client = init_client();
+    let dir = std::env::temp_dir().join("hydrus_api_thumbnails_sync");
+    client
+        .set_thumbnail_cache(hydrus_api::cache::ThumbnailCache::new(dir.clone(), 1 << 20).unwrap());
+    let file = SingleFile::Hash(first_hash(&client));
+    let first = client.get_thumbnail(file.clone(), true).unwrap();
+    let second = client.get_thumbnail(file, true).unwrap();
+    if let (Thumbnail::Image { bytes: a, .. }, Thumbnail::Image { bytes: b, .. }) = (first, second)
+    {
+        assert_eq!(a, b);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_render_file() {
+    let client = init_client();
+    let search = client
+        .search_files(
+            &[
+                SearchTag::System(SystemPredicate::Filetype(vec!["image".to_string()])),
+                SearchTag::System(SystemPredicate::Limit(1)),
+            ],
+            None,
+            None,
+            None,
+            None,
+            SearchReturns::Hashes,
+        )
+        .unwrap();
+    let options = RenderOptions {
+        format: Some(RenderFormat::Png),
+        ..Default::default()
+    };
+    let rendered = client
+        .render_file(SingleFile::Hash(search.hashes[0].clone()), options)
+        .unwrap();
+    assert_eq!(rendered.mime, "image/png");
+}
+
+#[test]
+fn test_local_file_access() {
+    let client = init_client();
+    let locations = client.local_file_storage_locations().unwrap();
+    assert!(!locations.is_empty());
+    let file = SingleFile::Hash(first_hash(&client));
+    let path = client.file_path(file.clone()).unwrap();
+    let access = hydrus_api::sync_lib::client::LocalFileAccess::new(&client).unwrap();
+    assert!(access.is_local());
+    let mut bytes = Vec::new();
+    std::io::Read::read_to_end(&mut access.open(file).unwrap(), &mut bytes).unwrap();
+    assert_eq!(bytes.len() as u64, path.size);
+}
+
+#[test]
+fn test_file_hashes() {
+    let client = init_client();
+    let hash = first_hash(&client);
+    let md5 = client
+        .file_hashes(std::slice::from_ref(&hash), HashType::Sha256, HashType::Md5)
+        .unwrap();
+    let sha256 = client
+        .file_hashes(
+            &md5.values().cloned().collect::<Vec<_>>(),
+            HashType::Md5,
+            HashType::Sha256,
+        )
+        .unwrap();
+    assert!(sha256.values().all(|converted| *converted == hash));
+}
+
+#[test]
+fn test_add_tags() {
+    let client = init_client();
+    let service = client.get_service_name("my tags").unwrap();
+    let (_guard, hash) = import_test_file(&client);
+    let file = HydrusFile::Hash(hash.clone());
+    for action in [TagAction::Add, TagAction::Delete] {
+        let actions = HashMap::from([(
+            service.service_key.clone(),
+            vec![action("hydrus_api test".to_string())],
+        )]);
+        client.add_tags(file.clone(), actions, None, None).unwrap();
+    }
+    delete_test_file(&client, hash);
+}
+
+#[test]
+fn test_clean_tags() {
+    let client = init_client();
+    // the hand-written cases the local cleaning is tested against
+    let fixtures: Vec<serde_json::Value> =
+        serde_json::from_str(include_str!("fixtures/clean_tags_cases.json")).unwrap();
+    for fixture in fixtures {
+        let tag = fixture["tag"].as_str().unwrap().to_string();
+        let clean = client.clean_tags(std::slice::from_ref(&tag)).unwrap();
+        let local = Tag::new(&tag).map(|tag| tag.to_string());
+        assert_eq!(clean.first(), local.as_ref(), "{tag:?}");
+    }
+}
+
+#[test]
+fn test_search_tags() {
+    let mut client = init_client();
+    client.set_tag_search_cache(hydrus_api::cache::TagSearchCache::new(
+        8,
+        std::time::Duration::from_secs(60),
+    ));
+    let tags = client
+        .search_tags("bl", None, None, TagDisplayType::Display)
+        .unwrap();
+    let mut narrowed = client
+        .search_tags("blu", None, None, TagDisplayType::Display)
+        .unwrap();
+    assert!(narrowed.iter().all(|tag| tags.contains(tag)));
+    // the narrowed result must match what the client itself returns
+    let mut uncached = init_client()
+        .search_tags("blu", None, None, TagDisplayType::Display)
+        .unwrap();
+    narrowed.sort_by(|a, b| a.tag.cmp(&b.tag));
+    uncached.sort_by(|a, b| a.tag.cmp(&b.tag));
+    assert_eq!(narrowed, uncached);
+}
+
+#[test]
+fn test_tag_resolver() {
+    let client = init_client();
+    let service = client.get_service_name("my tags").unwrap();
+    let tags = vec!["blue eyes".to_string()];
+    let relations = client.get_siblings_and_parents(&tags).unwrap();
+    assert!(relations["blue eyes"].contains_key(&service.service_key));
+    let resolver = client.tag_resolver(&tags, &service.service_key).unwrap();
+    let ideal = resolver.ideal_tag("blue eyes").to_string();
+    assert!(resolver.resolve(["blue eyes"]).contains(&ideal));
+}
+
+#[test]
+fn test_favourite_tags() {
+    let client = init_client();
+    let tag = "hydrus_api favourite".to_string();
+    let favourites = client
+        .set_favourite_tags(FavouriteTagsChange::Add(vec![tag.clone()]))
+        .unwrap();
+    assert!(favourites.contains(&tag));
+    assert_eq!(client.get_favourite_tags().unwrap(), favourites);
+    let favourites = client
+        .set_favourite_tags(FavouriteTagsChange::Remove(vec![tag.clone()]))
+        .unwrap();
+    assert!(!favourites.contains(&tag));
+}
+
+#[test]
+fn test_associate_url() {
+    let client = init_client();
+    let file = HydrusFile::Hash(first_hash(&client));
+    let url = "https://example.com/hydrus_api".to_string();
+    client
+        .associate_url(file.clone(), vec![url.clone()], vec![], None)
+        .unwrap();
+    client.associate_url(file, vec![], vec![url], None).unwrap();
+}
+
+#[test]
+fn test_get_url_info() {
+    let client = init_client();
+    let info = client
+        .get_url_info("https://example.com/hydrus_api")
+        .unwrap();
+    assert_eq!(info.url_type, UrlType::Unknown);
+}
+
+#[test]
+fn test_notes() {
+    let client = init_client();
+    let file = SingleFile::Hash(first_hash(&client));
+    let notes = HashMap::from([("hydrus_api".to_string(), "test note".to_string())]);
+    let merge = NoteMerge::Clever {
+        extend_existing_note_if_possible: true,
+        conflict_resolution: NoteConflict::Rename,
+    };
+    let stored = client
+        .set_notes(file.clone(), notes.clone(), merge)
+        .unwrap();
+    assert_eq!(stored, notes);
+    client
+        .delete_notes(file, vec!["hydrus_api".to_string()])
+        .unwrap();
+}
+
+#[test]
+fn test_set_rating() {
+    let client = init_client();
+    let services = client.get_services().unwrap();
+    let Some(service) = services
+        .into_values()
+        .find(|service| service.r#type == ServiceType::BoolRating)
+    else {
+        return;
+    };
+    let (_guard, hash) = import_test_file(&client);
+    let file = SingleFile::Hash(hash.clone());
+    assert!(
+        client
+            .set_rating(file.clone(), &service, RatingValue::Stars(1))
+            .is_err()
+    );
+    client
+        .set_rating(file.clone(), &service, RatingValue::Like)
+        .unwrap();
+    let metadata = client
+        .file_metadata(file.into(), FileMetadataOptions::default())
+        .unwrap();
+    assert_eq!(
+        metadata[0].ratings.get(&service.service_key),
+        Some(&Some(FileRating::Boolean(true)))
+    );
+    delete_test_file(&client, hash);
+}
+
+#[test]
+fn test_file_times() {
+    let client = init_client();
+    let (_guard, hash) = import_test_file(&client);
+    let file = SingleFile::Hash(hash.clone());
+    client
+        .set_time(
+            file.clone(),
+            TimestampType::ModifiedOnDisk,
+            Some(1_500_000_000_123),
+        )
+        .unwrap();
+    client
+        .increment_file_viewtime(file.clone(), CanvasType::ClientApi, 1.5, None, None)
+        .unwrap();
+    let options = FileMetadataOptions {
+        include_milliseconds: true,
+        ..Default::default()
+    };
+    let metadata = client.file_metadata(file.into(), options).unwrap();
+    let modified = metadata[0].time_modified_details["local"];
+    assert_eq!((modified * 1000.0).round() as u64, 1_500_000_000_123);
+    delete_test_file(&client, hash);
+}
+
+#[test]
+fn test_get_file_relationships() {
+    let client = init_client();
+    let hash = first_hash(&client);
+    let relationships = client
+        .get_file_relationships(HydrusFile::Hash(hash.clone()), None)
+        .unwrap();
+    assert!(relationships.contains_key(&hash));
+}
+
+#[test]
+fn test_potentials() {
+    let client = init_client();
+    let search = PotentialsSearch {
+        pixel_duplicates: PixelDuplicates::Required,
+        ..Default::default()
+    };
+    let count = client.get_potentials_count(&search).unwrap();
+    let pairs = client.get_potential_pairs(&search, Some(5)).unwrap();
+    assert!(pairs.len() as u64 <= count.min(5));
+    let random = client.get_random_potentials(&search).unwrap();
+    assert_eq!(random.is_empty(), count == 0);
+}
+
+#[test]
+fn test_resolve_duplicates_dry_run() {
+    use hydrus_api::rules::*;
+
+    let client = init_client();
+    let mut rules = DuplicateRules::new(vec![Box::new(HigherResolution), Box::new(LargerFilesize)]);
+    rules.max_pairs = Some(10);
+    let report = client.resolve_duplicates(&rules).unwrap();
+    assert!(!report.applied);
+    assert!(report.resolutions.len() + report.skipped.len() <= 10);
+}